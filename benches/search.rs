@@ -0,0 +1,221 @@
+//! Compares indexed searches against the linear scan over the bundled corpus,
+//! the linear scan's bounded distances against exhaustive ones, ranked
+//! searches against unranked ones, and mapping the corpus against reading it.
+//!
+//! Run with `cargo bench`. Each search is repeated for a fixed set of
+//! misspelled queries, once to warm up and then in several timed passes, and
+//! the median time per query is reported.
+
+use std::time::{Duration, Instant};
+
+use fuzzy_search::{ByDistance, FuzzySearcher, IndexKind, Levenshtein, Metric, Threshold};
+
+const CORPUS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/corpus/words.txt");
+
+const QUERIES: &[&str] = &[
+    "helo",
+    "wrold",
+    "teh",
+    "recieve",
+    "seperate",
+    "definately",
+    "accomodate",
+    "occured",
+];
+
+/// The number of timed passes each measurement takes the median of.
+const PASSES: usize = 5;
+
+/// Runs `search` over every query in a warm-up pass and then in each of
+/// [`PASSES`] timed passes, and returns the median time per query.
+fn time_per_query(mut search: impl FnMut(&str)) -> Duration {
+    median_time(|| {
+        for query in QUERIES {
+            search(query);
+        }
+    }) / QUERIES.len() as u32
+}
+
+/// Runs `run` once to warm up, then [`PASSES`] more times, and returns the
+/// median time taken.
+fn median_time(mut run: impl FnMut()) -> Duration {
+    run();
+
+    let mut times: Vec<Duration> = (0..PASSES)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .collect();
+    times.sort_unstable();
+    times[PASSES / 2]
+}
+
+fn report(name: &str, linear: Duration, indexed: Duration) {
+    println!(
+        "{name:<24} linear {linear:>12.2?}  indexed {indexed:>12.2?}  speedup {:>6.1}x",
+        linear.as_secs_f64() / indexed.as_secs_f64()
+    );
+}
+
+fn bench_index(kind: IndexKind, linear: &FuzzySearcher) {
+    let start = Instant::now();
+    let indexed = FuzzySearcher::new(CORPUS_PATH)
+        .expect("bundled corpus should load")
+        .with_index(kind)
+        .expect("index should be compatible with the default metric");
+    let built = start.elapsed();
+
+    let path = std::env::temp_dir().join("fuzzy_search_bench.idx");
+    indexed.save_index(&path).expect("index should save");
+    let loaded = median_time(|| {
+        FuzzySearcher::new(CORPUS_PATH)
+            .expect("bundled corpus should load")
+            .load_index(&path)
+            .expect("saved index should load");
+    });
+    let mapped = median_time(|| {
+        // SAFETY: the index file is not modified until it is removed, after
+        // the searcher is dropped.
+        unsafe {
+            FuzzySearcher::new(CORPUS_PATH)
+                .expect("bundled corpus should load")
+                .load_index_mapped(&path)
+        }
+        .expect("saved index should map");
+    });
+    std::fs::remove_file(&path).expect("index file should be removable");
+
+    println!("{kind:?}: built in {built:.2?}, loaded in {loaded:.2?}, mapped in {mapped:.2?}");
+
+    report(
+        "search",
+        time_per_query(|query| {
+            linear.search(query).unwrap();
+        }),
+        time_per_query(|query| {
+            indexed.search(query).unwrap();
+        }),
+    );
+    report(
+        "search_top_k(5)",
+        time_per_query(|query| {
+            linear.search_top_k(query, 5).unwrap();
+        }),
+        time_per_query(|query| {
+            indexed.search_top_k(query, 5).unwrap();
+        }),
+    );
+    report(
+        "search_within(1 edit)",
+        time_per_query(|query| {
+            linear
+                .search_within(query, Threshold::MaxDistance(1.0))
+                .unwrap();
+        }),
+        time_per_query(|query| {
+            indexed
+                .search_within(query, Threshold::MaxDistance(1.0))
+                .unwrap();
+        }),
+    );
+}
+
+/// Finds the closest word by computing every distance in full, and by
+/// rejecting words once they are known to be further than the closest so far.
+fn bench_bounded_distance() {
+    let corpus = std::fs::read_to_string(CORPUS_PATH).expect("bundled corpus should load");
+    let words: Vec<&str> = corpus.lines().collect();
+    let metric = Levenshtein::new();
+
+    let exhaustive = time_per_query(|query| {
+        let mut best = f64::INFINITY;
+        for word in &words {
+            best = best.min(metric.distance(query, word));
+        }
+    });
+    let bounded = time_per_query(|query| {
+        let mut best = f64::INFINITY;
+        for word in &words {
+            if let Some(distance) = metric.distance_within(query, word, best) {
+                best = distance;
+            }
+        }
+    });
+
+    println!(
+        "{:<24} exhaustive {exhaustive:>8.2?}  bounded {bounded:>12.2?}  speedup {:>6.1}x",
+        "closest distance",
+        exhaustive.as_secs_f64() / bounded.as_secs_f64()
+    );
+}
+
+/// Compares reading the corpus into memory against memory-mapping it.
+fn bench_loading() {
+    let loaded = median_time(|| {
+        FuzzySearcher::new(CORPUS_PATH).expect("bundled corpus should load");
+    });
+    let mapped = median_time(|| {
+        // SAFETY: the bundled corpus is not modified while the benchmark runs.
+        unsafe { FuzzySearcher::new_mapped(CORPUS_PATH) }.expect("bundled corpus should map");
+    });
+
+    println!(
+        "{:<24} loaded {loaded:>12.2?}  mapped {mapped:>13.2?}  speedup {:>6.1}x",
+        "open corpus",
+        loaded.as_secs_f64() / mapped.as_secs_f64()
+    );
+}
+
+/// Compares indexed searches with and without a ranking that only breaks
+/// ties by weight.
+fn bench_ranking() {
+    let unranked = FuzzySearcher::new(CORPUS_PATH)
+        .expect("bundled corpus should load")
+        .with_index(IndexKind::LevenshteinAutomaton)
+        .expect("index should be compatible with the default metric");
+    let ranked = FuzzySearcher::new(CORPUS_PATH)
+        .expect("bundled corpus should load")
+        .with_index(IndexKind::LevenshteinAutomaton)
+        .expect("index should be compatible with the default metric")
+        .with_ranking(ByDistance::new());
+
+    let unranked = time_per_query(|query| {
+        unranked.search(query).unwrap();
+    });
+    let ranked = time_per_query(|query| {
+        ranked.search(query).unwrap();
+    });
+
+    println!(
+        "{:<24} unranked {unranked:>10.2?}  ranked {ranked:>13.2?}  slowdown {:>5.1}x",
+        "ranked search",
+        ranked.as_secs_f64() / unranked.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench_loading();
+    bench_bounded_distance();
+    bench_ranking();
+
+    let linear = FuzzySearcher::new(CORPUS_PATH).expect("bundled corpus should load");
+
+    bench_index(IndexKind::BkTree, &linear);
+    bench_index(
+        IndexKind::SymSpell {
+            max_distance: 2,
+            prefix_length: 7,
+        },
+        &linear,
+    );
+    bench_index(IndexKind::LevenshteinAutomaton, &linear);
+    bench_index(
+        IndexKind::NGram {
+            gram_size: 3,
+            min_overlap: 0.5,
+        },
+        &linear,
+    );
+}
@@ -0,0 +1,684 @@
+//! Compact storage for the strings being searched.
+//!
+//! A [`Corpus`] keeps every string in one contiguous buffer, with a table of
+//! the byte range of each string. This costs 8 bytes per string rather than
+//! a separate heap allocation and a 24-byte `String`, and keeps neighbouring
+//! strings next to each other in memory during linear scans.
+//!
+//! The buffer is either owned or a memory-mapped corpus file. Mapped corpora
+//! share their pages with every other process mapping the same file, and
+//! only need the table of ranges to be built when they are opened.
+//!
+//! Strings may also carry a weight, such as how often a word occurs, which a
+//! [`FuzzySearcher`](crate::FuzzySearcher) can blend into its ranking with
+//! [`FuzzySearcher::with_ranking`](crate::FuzzySearcher::with_ranking).
+//!
+//! Corpus files are parsed as described by a [`CorpusFormat`].
+
+use std::{
+    collections::HashMap, fmt, io::Read, iter::FusedIterator, ops::Range, path::Path, sync::Arc,
+};
+
+use memmap2::Mmap;
+
+use crate::FuzzySearchError;
+
+/// How corpus text is split into entries.
+///
+/// Corpus text holds one entry per line. Lines may end with `\n` or `\r\n`,
+/// a leading UTF-8 byte order mark is ignored, and blank lines are skipped.
+/// Comments, deduplication and weights are disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CorpusFormat {
+    comments: bool,
+    dedup: bool,
+    weights: bool,
+}
+
+impl CorpusFormat {
+    /// Creates a format with comments, deduplication and weights disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables skipping lines that start with `#`.
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Enables or disables skipping entries identical to an earlier entry,
+    /// so that each entry appears once, at the position it first appeared.
+    ///
+    /// In weighted corpora, the weights of the skipped entries are added to
+    /// the weight of the entry that is kept.
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Enables or disables reading a weight, such as a word frequency, from
+    /// the last tab-separated column of every line, as in `the\t23135851162`.
+    ///
+    /// Weights must be finite and non-negative. The entry is the text before
+    /// the last tab, and lines whose entry is blank are skipped like blank
+    /// lines.
+    pub fn with_weights(mut self, weights: bool) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Loads a corpus in this format from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError` if the corpus file cannot be opened or read,
+    /// is not valid UTF-8, has a line without a valid weight, or exceeds 4 GiB.
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Corpus, FuzzySearchError> {
+        crate::load_corpus(path, *self)
+    }
+
+    /// Memory-maps a corpus file in this format. See
+    /// [`FuzzySearcher::new_mapped`](crate::FuzzySearcher::new_mapped).
+    ///
+    /// # Safety
+    ///
+    /// The corpus file must not be modified or truncated while the corpus
+    /// exists, including by other processes. Doing so is undefined behavior.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError` if the corpus file cannot be opened or read,
+    /// is not valid UTF-8, has a line without a valid weight, or exceeds 4 GiB.
+    pub unsafe fn map<P: AsRef<Path>>(&self, path: P) -> Result<Corpus, FuzzySearchError> {
+        // SAFETY: the caller guarantees that the file is not modified while
+        // the corpus exists.
+        unsafe { crate::map_corpus(path, *self) }
+    }
+
+    /// Reads a corpus in this format from a reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError` if the reader fails, does not produce valid
+    /// UTF-8, produces a line without a valid weight, or exceeds 4 GiB.
+    pub fn read<R: Read>(&self, reader: R) -> Result<Corpus, FuzzySearchError> {
+        crate::read_corpus(reader, None, *self)
+    }
+
+    /// Parses corpus text in this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::InvalidCorpusWeight` if weights are enabled
+    /// and a line does not end with a valid weight, or
+    /// `FuzzySearchError::CorpusTooLarge` if the text exceeds 4 GiB.
+    pub fn parse(&self, text: &str) -> Result<Corpus, FuzzySearchError> {
+        let Entries { spans, weights } = self.split(text, None)?;
+
+        Ok(Corpus::from_text(text.to_string(), spans).with_weights(weights))
+    }
+
+    /// Splits corpus text into entries, returning the byte range of each and,
+    /// if weights are enabled, the weight of each.
+    ///
+    /// Errors are reported against `path`, the file the text was read from.
+    pub(crate) fn split(
+        &self,
+        text: &str,
+        path: Option<&Path>,
+    ) -> Result<Entries, FuzzySearchError> {
+        // Every offset into the text must fit in a span.
+        if u32::try_from(text.len()).is_err() {
+            return Err(FuzzySearchError::CorpusTooLarge {
+                path: path.map(Path::to_path_buf),
+            });
+        }
+
+        // The position of each distinct entry kept so far, if deduplicating.
+        let mut seen = HashMap::new();
+        let mut spans = Vec::new();
+        let mut weights = Vec::new();
+
+        // A byte order mark is not part of the first line.
+        let mut start = if text.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        };
+
+        for (number, line) in text[start..].split('\n').enumerate() {
+            let line_start = start;
+            start += line.len() + 1;
+
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.trim().is_empty() || (self.comments && line.starts_with('#')) {
+                continue;
+            }
+
+            let (entry, weight) = if self.weights {
+                line.rsplit_once('\t')
+                    .and_then(|(entry, weight)| Some((entry, parse_weight(weight)?)))
+                    .ok_or_else(|| FuzzySearchError::InvalidCorpusWeight {
+                        path: path.map(Path::to_path_buf),
+                        line: number + 1,
+                    })?
+            } else {
+                (line, 1.0)
+            };
+            if entry.trim().is_empty() {
+                continue;
+            }
+
+            if self.dedup {
+                if let Some(&position) = seen.get(entry) {
+                    if self.weights {
+                        weights[position] += weight;
+                    }
+                    continue;
+                }
+                seen.insert(entry, spans.len());
+            }
+
+            spans.push((to_offset(line_start), to_offset(line_start + entry.len())));
+            if self.weights {
+                weights.push(weight);
+            }
+        }
+
+        Ok(Entries { spans, weights })
+    }
+}
+
+/// The entries split from corpus text by a [`CorpusFormat`].
+pub(crate) struct Entries {
+    /// The byte range of each entry.
+    pub(crate) spans: Vec<(u32, u32)>,
+    /// The weight of each entry, or empty if the format is unweighted.
+    pub(crate) weights: Vec<f64>,
+}
+
+/// Parses a corpus weight, which must be finite and non-negative.
+fn parse_weight(weight: &str) -> Option<f64> {
+    weight
+        .trim()
+        .parse()
+        .ok()
+        .filter(|weight: &f64| weight.is_finite() && *weight >= 0.0)
+}
+
+/// The buffer holding the text of every string in a corpus.
+#[derive(Debug, Clone)]
+enum Text {
+    Owned(String),
+    /// A memory-mapped file that has been checked to be valid UTF-8.
+    Mapped(Arc<Mmap>),
+}
+
+impl Text {
+    fn as_str(&self) -> &str {
+        match self {
+            Text::Owned(text) => text,
+            // SAFETY: `Corpus::from_mapped` requires mapped text to be valid
+            // UTF-8, and the mapping is read-only.
+            Text::Mapped(mmap) => unsafe { std::str::from_utf8_unchecked(mmap) },
+        }
+    }
+
+    /// Returns the owned text, copying mapped text into memory first.
+    fn to_mut(&mut self) -> &mut String {
+        if let Text::Mapped(_) = self {
+            *self = Text::Owned(self.as_str().to_string());
+        }
+
+        match self {
+            Text::Owned(text) => text,
+            Text::Mapped(_) => unreachable!("mapped text was just copied"),
+        }
+    }
+}
+
+/// An append-only list of strings stored in a single buffer.
+///
+/// Strings keep the position they were added at, which is the index reported
+/// in search results. Each string has a weight, which is 1 unless the corpus
+/// was built with weights.
+#[derive(Clone)]
+pub struct Corpus {
+    text: Text,
+    /// The byte range of each string in `text`.
+    spans: Vec<(u32, u32)>,
+    /// The weight of each string, or empty if every string weighs 1.
+    weights: Vec<f64>,
+}
+
+impl Corpus {
+    /// Creates an empty corpus.
+    pub fn new() -> Self {
+        Self {
+            text: Text::Owned(String::new()),
+            spans: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+
+    /// Creates an empty corpus with room for `strings` strings totalling
+    /// `bytes` bytes.
+    pub fn with_capacity(strings: usize, bytes: usize) -> Self {
+        Self {
+            text: Text::Owned(String::with_capacity(bytes)),
+            spans: Vec::with_capacity(strings),
+            weights: Vec::new(),
+        }
+    }
+
+    /// Creates a corpus of the given byte ranges of `text`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a range is out of bounds or does not lie on character
+    /// boundaries.
+    pub(crate) fn from_text(text: String, spans: Vec<(u32, u32)>) -> Self {
+        check_spans(&text, &spans);
+
+        Self {
+            text: Text::Owned(text),
+            spans,
+            weights: Vec::new(),
+        }
+    }
+
+    /// Creates a corpus of the given byte ranges of a memory-mapped file.
+    ///
+    /// Appending to the corpus copies the file's text into memory.
+    ///
+    /// # Safety
+    ///
+    /// The mapped file must be valid UTF-8, and must not be modified while
+    /// the corpus or any of its clones exist.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a range is out of bounds or does not lie on character
+    /// boundaries.
+    pub(crate) unsafe fn from_mapped(mmap: Mmap, spans: Vec<(u32, u32)>) -> Self {
+        let text = Text::Mapped(Arc::new(mmap));
+        check_spans(text.as_str(), &spans);
+
+        Self {
+            text,
+            spans,
+            weights: Vec::new(),
+        }
+    }
+
+    /// Sets the weight of each string, or makes every string weigh 1 if
+    /// `weights` is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not one weight per string.
+    pub(crate) fn with_weights(mut self, weights: Vec<f64>) -> Self {
+        assert!(
+            weights.is_empty() || weights.len() == self.len(),
+            "corpus has {} strings but {} weights",
+            self.len(),
+            weights.len()
+        );
+
+        self.weights = weights;
+        self
+    }
+
+    /// Returns `true` if the corpus text is a memory-mapped file rather than
+    /// owned by the corpus.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.text, Text::Mapped(_))
+    }
+
+    /// Returns `true` if the strings in the corpus have been given weights.
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
+    /// Appends a string with a weight of 1 to the end of the corpus.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the corpus would grow beyond 4 GiB of text.
+    pub fn push(&mut self, text: &str) {
+        self.push_text(text);
+        if self.is_weighted() {
+            self.weights.push(1.0);
+        }
+    }
+
+    /// Appends a string with the given weight to the end of the corpus.
+    ///
+    /// Strings already in an unweighted corpus are given a weight of 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weight is negative or not finite, or if the corpus would
+    /// grow beyond 4 GiB of text.
+    pub fn push_weighted(&mut self, text: &str, weight: f64) {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "corpus weight {weight} is not finite and non-negative"
+        );
+
+        if !self.is_weighted() {
+            self.weights = vec![1.0; self.len()];
+        }
+        self.push_text(text);
+        self.weights.push(weight);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let buffer = self.text.to_mut();
+        let start = buffer.len();
+        buffer.push_str(text);
+
+        self.spans.push((to_offset(start), to_offset(buffer.len())));
+    }
+
+    /// Returns the number of strings in the corpus.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Returns `true` if the corpus contains no strings.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the string at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        let &(start, end) = self.spans.get(index)?;
+        Some(&self.text.as_str()[start as usize..end as usize])
+    }
+
+    /// Returns the weight of the string at `index`, or `None` if it is out of
+    /// bounds.
+    pub fn weight(&self, index: usize) -> Option<f64> {
+        if index >= self.len() {
+            return None;
+        }
+
+        Some(self.weights.get(index).copied().unwrap_or(1.0))
+    }
+
+    /// Returns the largest weight of any string, or 1 if the corpus is
+    /// unweighted.
+    pub(crate) fn max_weight(&self) -> f64 {
+        if self.is_weighted() {
+            self.weights.iter().copied().fold(0.0, f64::max)
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns an iterator over the strings in the corpus, in order.
+    pub fn iter(&self) -> Iter<'_> {
+        self.range(0..self.len())
+    }
+
+    /// Returns an iterator over the strings at the given positions.
+    pub(crate) fn range(&self, range: Range<usize>) -> Iter<'_> {
+        Iter {
+            corpus: self,
+            range,
+        }
+    }
+}
+
+/// Converts a byte offset into a corpus buffer into the stored form.
+///
+/// # Panics
+///
+/// Panics if the offset is beyond 4 GiB.
+pub(crate) fn to_offset(offset: usize) -> u32 {
+    u32::try_from(offset).expect("corpus text exceeds 4 GiB")
+}
+
+fn check_spans(text: &str, spans: &[(u32, u32)]) {
+    for &(start, end) in spans {
+        assert!(
+            text.get(start as usize..end as usize).is_some(),
+            "corpus span {start}..{end} is not a valid range of the corpus text"
+        );
+    }
+}
+
+impl Default for Corpus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats a list of the strings in the corpus, paired with their weights if
+/// the corpus is weighted.
+impl fmt::Debug for Corpus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_weighted() {
+            f.debug_list()
+                .entries(self.iter().zip(&self.weights))
+                .finish()
+        } else {
+            f.debug_list().entries(self.iter()).finish()
+        }
+    }
+}
+
+/// Corpora are equal if they contain the same strings with the same weights in
+/// the same order, however their text is stored.
+impl PartialEq for Corpus {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+            && (0..self.len()).all(|index| self.weight(index) == other.weight(index))
+    }
+}
+
+impl Eq for Corpus {}
+
+impl std::ops::Index<usize> for Corpus {
+    type Output = str;
+
+    fn index(&self, index: usize) -> &str {
+        match self.get(index) {
+            Some(text) => text,
+            None => panic!(
+                "corpus index {index} is out of bounds for a corpus of {} strings",
+                self.len()
+            ),
+        }
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for Corpus {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut corpus = Self::new();
+        corpus.extend(iter);
+        corpus
+    }
+}
+
+impl<S: AsRef<str>> Extend<S> for Corpus {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.spans.reserve(iter.size_hint().0);
+        if self.is_weighted() {
+            self.weights.reserve(iter.size_hint().0);
+        }
+
+        for text in iter {
+            self.push(text.as_ref());
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Corpus {
+    type Item = &'a str;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the strings in a [`Corpus`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    corpus: &'a Corpus,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let index = self.range.next()?;
+        Some(&self.corpus[index])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.range.next_back()?;
+        Some(&self.corpus[index])
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_keep_their_positions() {
+        let corpus: Corpus = ["apple", "", "caf\u{e9}", "banana"].into_iter().collect();
+
+        assert_eq!(corpus.len(), 4);
+        assert_eq!(&corpus[0], "apple");
+        assert_eq!(&corpus[1], "");
+        assert_eq!(corpus.get(2), Some("caf\u{e9}"));
+        assert_eq!(corpus.get(4), None);
+        assert_eq!(
+            corpus.iter().collect::<Vec<_>>(),
+            ["apple", "", "caf\u{e9}", "banana"]
+        );
+        assert_eq!(corpus.iter().next_back(), Some("banana"));
+        assert_eq!(corpus.range(1..3).len(), 2);
+    }
+
+    #[test]
+    fn empty_corpora_have_no_strings() {
+        let corpus = Corpus::new();
+
+        assert!(corpus.is_empty());
+        assert_eq!(corpus.get(0), None);
+        assert_eq!(corpus.iter().next(), None);
+        assert_eq!(corpus, Corpus::with_capacity(10, 100));
+    }
+
+    #[test]
+    fn corpora_can_reference_ranges_of_a_buffer() {
+        let mut corpus = Corpus::from_text("apple\nbanana\n".to_string(), vec![(0, 5), (6, 12)]);
+        assert_eq!(corpus, ["apple", "banana"].into_iter().collect());
+        assert!(!corpus.is_mapped());
+
+        corpus.push("cherry");
+        assert_eq!(&corpus[2], "cherry");
+        assert_eq!(format!("{corpus:?}"), r#"["apple", "banana", "cherry"]"#);
+    }
+
+    #[test]
+    fn line_endings_byte_order_marks_and_blank_lines_are_ignored() {
+        let corpus = CorpusFormat::new()
+            .parse("\u{feff}apple\r\n\r\n  \nbanana\r\n# note\n\napple\n")
+            .unwrap();
+        assert_eq!(
+            corpus.iter().collect::<Vec<_>>(),
+            ["apple", "banana", "# note", "apple"]
+        );
+    }
+
+    #[test]
+    fn comments_and_duplicates_can_be_skipped() {
+        let text = "# fruit\napple\nbanana\n #indented\napple\r\nbanana\n";
+
+        let comments = CorpusFormat::new().with_comments(true).parse(text).unwrap();
+        assert_eq!(
+            comments.iter().collect::<Vec<_>>(),
+            ["apple", "banana", " #indented", "apple", "banana"]
+        );
+
+        let deduped = CorpusFormat::new()
+            .with_comments(true)
+            .with_dedup(true)
+            .parse(text)
+            .unwrap();
+        assert_eq!(
+            deduped.iter().collect::<Vec<_>>(),
+            ["apple", "banana", " #indented"]
+        );
+    }
+
+    #[test]
+    fn weights_are_read_from_the_last_column() {
+        let text = "# word\tcount\nthe\t100\r\n\t5\nthy\t2\nNew York\t3.5\n \t1\nthe\t20\n";
+        let format = CorpusFormat::new().with_comments(true).with_weights(true);
+
+        let corpus = format.parse(text).unwrap();
+        assert!(corpus.is_weighted());
+        assert_eq!(
+            format!("{corpus:?}"),
+            r#"[("the", 100.0), ("thy", 2.0), ("New York", 3.5), ("the", 20.0)]"#
+        );
+
+        let deduped = format.with_dedup(true).parse(text).unwrap();
+        assert_eq!(deduped.len(), 3);
+        assert_eq!(deduped.weight(0), Some(120.0));
+        assert_eq!(deduped.weight(3), None);
+
+        for (invalid, line) in [("the\t1\nthy\n", 2), ("\nthe\t-1\n", 2), ("the\tNaN", 1)] {
+            assert!(matches!(
+                format.parse(invalid),
+                Err(FuzzySearchError::InvalidCorpusWeight { path: None, line: l }) if l == line
+            ));
+        }
+    }
+
+    #[test]
+    fn pushed_strings_weigh_one_unless_given_a_weight() {
+        let mut corpus: Corpus = ["apple", "banana"].into_iter().collect();
+        assert!(!corpus.is_weighted());
+        assert_eq!(corpus.weight(1), Some(1.0));
+
+        corpus.push_weighted("cherry", 5.0);
+        corpus.push("damson");
+        assert!(corpus.is_weighted());
+        assert_eq!(
+            (0..4).map(|index| corpus.weight(index)).collect::<Vec<_>>(),
+            [Some(1.0), Some(1.0), Some(5.0), Some(1.0)]
+        );
+
+        let unweighted: Corpus = ["apple", "banana", "cherry", "damson"]
+            .into_iter()
+            .collect();
+        assert_ne!(corpus, unweighted);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a valid range")]
+    fn spans_must_lie_on_character_boundaries() {
+        Corpus::from_text("caf\u{e9}".to_string(), vec![(0, 4)]);
+    }
+}
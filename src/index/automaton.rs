@@ -0,0 +1,390 @@
+//! Levenshtein automaton search over a trie of the corpus.
+//!
+//! The corpus keys are sorted and stored in a character trie, so keys that
+//! share a prefix share a path. A query is compiled into a Levenshtein
+//! automaton that accepts exactly the strings within `max_distance` edits of
+//! it, and the automaton is run along every path of the trie at once. As
+//! soon as the automaton can no longer reach an accepting state, the whole
+//! subtree below that trie node is skipped, so each shared prefix is
+//! examined once rather than once per key.
+
+use std::collections::BinaryHeap;
+
+use super::{Decoder, Encoder, Words, check_key_indexes};
+use crate::{Candidate, Corpus, Metric};
+
+/// A deterministic Levenshtein automaton for a fixed query and distance.
+///
+/// Each state is a row of the Levenshtein dynamic programming matrix for the
+/// characters consumed so far: `state[i]` is the distance between those
+/// characters and the first `i` characters of the query.
+struct LevenshteinAutomaton {
+    /// The scalar values of the query's characters.
+    query: Vec<u32>,
+    max_distance: usize,
+}
+
+impl LevenshteinAutomaton {
+    fn new(query: &str, max_distance: usize) -> Self {
+        Self {
+            query: query.chars().map(u32::from).collect(),
+            max_distance,
+        }
+    }
+
+    /// Writes the state before any characters have been consumed to `state`.
+    fn start(&self, state: &mut Vec<usize>) {
+        state.clear();
+        state.extend(0..=self.query.len());
+    }
+
+    /// Writes the state reached by consuming the character with scalar
+    /// value `c` from `prev` to `next`.
+    fn step(&self, prev: &[usize], c: u32, next: &mut Vec<usize>) {
+        next.clear();
+        next.push(prev[0] + 1);
+
+        for (i, &query_char) in self.query.iter().enumerate() {
+            let substitution_cost = usize::from(query_char != c);
+            let distance = (prev[i] + substitution_cost) // substitution
+                .min(prev[i + 1] + 1) // deletion
+                .min(next[i] + 1); // insertion
+            next.push(distance);
+        }
+    }
+
+    /// Returns the distance between the consumed characters and the query,
+    /// if it is within the automaton's maximum distance.
+    fn accepts(&self, state: &[usize]) -> Option<usize> {
+        let distance = state[self.query.len()];
+        (distance <= self.max_distance).then_some(distance)
+    }
+
+    /// Returns `true` if consuming more characters could still lead to an
+    /// accepting state.
+    fn can_accept(&self, state: &[usize]) -> bool {
+        state.iter().any(|&distance| distance <= self.max_distance)
+    }
+}
+
+/// The number of words describing each node.
+const NODE_LEN: usize = 4;
+/// The number of words describing each edge.
+const EDGE_LEN: usize = 2;
+
+/// A trie node while the trie is being built.
+struct Node {
+    /// The children of this node, sorted by the character on their edge.
+    children: Vec<(char, u32)>,
+    /// The position in [`Trie::key_indexes`] of the first key ending here.
+    first_key: u32,
+    /// The number of keys ending at this node.
+    key_count: u32,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            first_key: 0,
+            key_count: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Trie {
+    /// Every node in the trie: the position in `edges` of its first edge,
+    /// its number of edges, the position in `key_indexes` of the first key
+    /// ending at it and the number of keys ending at it. The root is the
+    /// first node.
+    nodes: Words,
+    /// Every edge in the trie: the scalar value of its character and the
+    /// child's node number. The edges of each node are sorted by character.
+    edges: Words,
+    /// The corpus indexes of the keys, in sorted key order.
+    key_indexes: Words,
+    /// The length of the longest key, in characters.
+    max_key_len: usize,
+}
+
+impl Trie {
+    /// Builds a trie containing every key.
+    pub(crate) fn build(keys: &Corpus) -> Self {
+        let mut key_indexes: Vec<u32> = (0..keys.len())
+            .map(|index| u32::try_from(index).expect("corpus is too large for a trie index"))
+            .collect();
+        key_indexes.sort_by(|&a, &b| keys[a as usize].cmp(&keys[b as usize]));
+
+        let mut nodes = vec![Node::new()];
+        let mut max_key_len = 0;
+
+        // Keys are inserted in sorted order, so any new edge is always
+        // added after the existing ones, and keys ending at the same node
+        // are adjacent in `key_indexes`.
+        for (position, &index) in key_indexes.iter().enumerate() {
+            let mut current = 0;
+            let mut len = 0;
+
+            for c in keys[index as usize].chars() {
+                len += 1;
+                current = match nodes[current].children.last() {
+                    Some(&(last, child)) if last == c => child as usize,
+                    _ => {
+                        let child = nodes.len();
+                        nodes.push(Node::new());
+                        nodes[current].children.push((c, child as u32));
+                        child
+                    }
+                };
+            }
+
+            let node = &mut nodes[current];
+            if node.key_count == 0 {
+                node.first_key = position as u32;
+            }
+            node.key_count += 1;
+            max_key_len = max_key_len.max(len);
+        }
+
+        let mut node_words = Vec::with_capacity(nodes.len() * NODE_LEN);
+        let mut edge_words = Vec::with_capacity(nodes.len() * EDGE_LEN);
+        for node in nodes {
+            node_words.extend([
+                (edge_words.len() / EDGE_LEN) as u32,
+                node.children.len() as u32,
+                node.first_key,
+                node.key_count,
+            ]);
+            for (c, child) in node.children {
+                edge_words.extend([u32::from(c), child]);
+            }
+        }
+
+        Self {
+            nodes: node_words.into(),
+            edges: edge_words.into(),
+            key_indexes: key_indexes.into(),
+            max_key_len,
+        }
+    }
+
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        out.usize(self.max_key_len);
+        out.table(&self.key_indexes, 1);
+        out.table(&self.nodes, NODE_LEN);
+        out.table(&self.edges, EDGE_LEN);
+    }
+
+    /// Decodes a trie, checking that every node refers to keys in the corpus
+    /// and that children always follow their parent, so the trie has no
+    /// cycles.
+    pub(crate) fn decode(input: &mut Decoder<'_>, keys: &Corpus) -> Result<Self, &'static str> {
+        const MALFORMED: &str = "index file holds a malformed trie";
+
+        let trie = Self {
+            max_key_len: input.usize()?,
+            key_indexes: input.table(1)?,
+            nodes: input.table(NODE_LEN)?,
+            edges: input.table(EDGE_LEN)?,
+        };
+        check_key_indexes(&trie.key_indexes, 1, 0, keys)?;
+
+        let len = trie.nodes.len() / NODE_LEN;
+        if len == 0 {
+            return Err(MALFORMED);
+        }
+
+        let mut next_edge = 0;
+        for node in 0..len {
+            let edges = trie.edge_range(node);
+            if edges.start != next_edge || edges.end > trie.edges.len() / EDGE_LEN {
+                return Err(MALFORMED);
+            }
+            next_edge = edges.end;
+
+            for edge in edges {
+                let position = edge * EDGE_LEN;
+                let child = trie.edges.get(position + 1) as usize;
+                if char::from_u32(trie.edges.get(position)).is_none()
+                    || child <= node
+                    || child >= len
+                {
+                    return Err(MALFORMED);
+                }
+            }
+
+            if trie.key_range(node).end > trie.key_indexes.len() {
+                return Err(MALFORMED);
+            }
+        }
+        if next_edge != trie.edges.len() / EDGE_LEN {
+            return Err(MALFORMED);
+        }
+
+        Ok(trie)
+    }
+
+    /// The positions of the edges from `node` to its children.
+    fn edge_range(&self, node: usize) -> std::ops::Range<usize> {
+        let first = self.nodes.get(node * NODE_LEN) as usize;
+        first..first + self.nodes.get(node * NODE_LEN + 1) as usize
+    }
+
+    /// The positions in `key_indexes` of the keys ending at `node`.
+    fn key_range(&self, node: usize) -> std::ops::Range<usize> {
+        let first = self.nodes.get(node * NODE_LEN + 2) as usize;
+        first..first + self.nodes.get(node * NODE_LEN + 3) as usize
+    }
+
+    /// Returns the corpus index of every key within `max_distance` edits of
+    /// `arg`, with its Levenshtein distance.
+    fn matches(&self, arg: &str, max_distance: usize) -> Vec<(usize, usize)> {
+        let automaton = LevenshteinAutomaton::new(arg, max_distance);
+        let mut matches = Vec::new();
+
+        // `states[depth]` holds the automaton state for the path to the node
+        // being visited at that depth. Nodes are visited depth first, so a
+        // node's parent state is never overwritten before the node is.
+        let mut states = vec![Vec::new()];
+        automaton.start(&mut states[0]);
+        let mut stack: Vec<(u32, u32, usize)> = Vec::new();
+
+        self.visit(0, &automaton, &states[0], &mut matches);
+        self.push_children(0, 1, &mut stack);
+
+        while let Some((node, c, depth)) = stack.pop() {
+            if states.len() <= depth {
+                states.push(Vec::new());
+            }
+            let (parents, rest) = states.split_at_mut(depth);
+            automaton.step(&parents[depth - 1], c, &mut rest[0]);
+
+            let state = &states[depth];
+            if !automaton.can_accept(state) {
+                continue;
+            }
+
+            self.visit(node as usize, &automaton, state, &mut matches);
+            self.push_children(node as usize, depth + 1, &mut stack);
+        }
+
+        matches
+    }
+
+    /// Records the keys ending at `node` if the automaton accepts them.
+    fn visit(
+        &self,
+        node: usize,
+        automaton: &LevenshteinAutomaton,
+        state: &[usize],
+        matches: &mut Vec<(usize, usize)>,
+    ) {
+        let keys = self.key_range(node);
+        if keys.is_empty() {
+            return;
+        }
+
+        if let Some(distance) = automaton.accepts(state) {
+            matches
+                .extend(keys.map(|position| (self.key_indexes.get(position) as usize, distance)));
+        }
+    }
+
+    fn push_children(&self, node: usize, depth: usize, stack: &mut Vec<(u32, u32, usize)>) {
+        stack.extend(self.edge_range(node).map(|edge| {
+            let position = edge * EDGE_LEN;
+            (
+                self.edges.get(position + 1),
+                self.edges.get(position),
+                depth,
+            )
+        }));
+    }
+
+    /// Finds every key within `max_distance` of `arg`, sorted from best to
+    /// worst match.
+    pub(crate) fn within_distance(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        max_distance: f64,
+    ) -> Vec<Candidate> {
+        if max_distance < 0.0 {
+            return Vec::new();
+        }
+
+        // The automaton only counts whole edits, so any fractional part of
+        // the bound can be dropped.
+        let edits = max_distance.min(self.max_key_len.max(arg.chars().count()) as f64) as usize;
+
+        let mut matches: Vec<Candidate> = self
+            .matches(arg, edits)
+            .into_iter()
+            .filter_map(|(index, _)| {
+                Candidate::within(arg, &keys[index], index, metric, max_distance)
+            })
+            .collect();
+
+        matches.sort_unstable();
+        matches
+    }
+
+    /// Finds the `k` keys closest to `arg`, sorted from best to worst match.
+    ///
+    /// The automaton's distance is raised one edit at a time until at least
+    /// `k` keys are found. Returns `None` if fewer keys than `k` (or than the
+    /// whole corpus) are within any number of edits, which happens when the
+    /// metric measures some keys as infinitely far away.
+    pub(crate) fn top_k(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        k: usize,
+    ) -> Option<Vec<Candidate>> {
+        let wanted = k.min(keys.len());
+        if wanted == 0 {
+            return Some(Vec::new());
+        }
+
+        // Every key is within this many edits of the query.
+        let max_edits = self.max_key_len.max(arg.chars().count());
+
+        for edits in 0..=max_edits {
+            let matches = self.within_distance(arg, keys, metric, edits as f64);
+            if matches.len() >= wanted {
+                let mut best: BinaryHeap<Candidate> = matches.into_iter().collect();
+                while best.len() > k {
+                    best.pop();
+                }
+                return Some(best.into_sorted_vec());
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::corpus;
+    use crate::{Hamming, find_top_k};
+
+    #[test]
+    fn top_k_gives_up_when_too_few_keys_are_in_reach() {
+        let keys = corpus();
+        let metric = Hamming::new();
+        let trie = Trie::build(&keys);
+
+        // Hamming distances between strings of different lengths are
+        // infinite, so only five keys are ever within reach of "helo".
+        assert_eq!(
+            trie.top_k("helo", &keys, &metric, 5),
+            Some(find_top_k("helo", &keys, &metric, 5))
+        );
+        assert_eq!(trie.top_k("helo", &keys, &metric, 6), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! A Burkhard-Keller tree over the corpus.
+//!
+//! Each node stores one corpus key, and each edge is labelled with the
+//! distance between the parent and child keys. When searching for keys
+//! within `radius` of a query that is `d` away from a node, the triangle
+//! inequality guarantees that only children whose edge distance lies within
+//! `d - radius ..= d + radius` can contain matches, so the rest of the tree
+//! is skipped.
+
+use std::collections::BinaryHeap;
+
+use super::{Decoder, Encoder, Words, check_key_indexes};
+use crate::{Candidate, Corpus, Metric};
+
+/// The number of words describing each node.
+const NODE_LEN: usize = 3;
+/// The number of words describing each edge.
+const EDGE_LEN: usize = 3;
+
+#[derive(Debug)]
+pub(crate) struct BkTree {
+    /// Every node in the tree: the corpus index of its key, the position in
+    /// `edges` of its first edge and its number of edges. The root, if any,
+    /// is the first node.
+    nodes: Words,
+    /// Every edge in the tree: the child's node number, then the low and
+    /// high halves of the bits of its distance from the parent.
+    edges: Words,
+}
+
+impl BkTree {
+    /// Builds a tree containing every key, inserted in corpus order.
+    pub(crate) fn build(keys: &Corpus, metric: &dyn Metric) -> Self {
+        // Node `n` holds the key with corpus index `n`, and `children[n]`
+        // its children, labelled with their distance from it.
+        let mut children: Vec<Vec<(f64, u32)>> = Vec::with_capacity(keys.len());
+
+        for index in 0..keys.len() {
+            children.push(Vec::new());
+            if index == 0 {
+                continue;
+            }
+
+            // Walk down from the root, following the edge labelled with the
+            // new key's distance from each node, until there is no such edge.
+            let mut current = 0;
+            loop {
+                let distance = metric.distance(&keys[index], &keys[current]);
+
+                match children[current].iter().find(|(edge, _)| *edge == distance) {
+                    Some(&(_, child)) => current = child as usize,
+                    None => {
+                        let index =
+                            u32::try_from(index).expect("corpus is too large for a BK-tree");
+                        children[current].push((distance, index));
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(children.len() * NODE_LEN);
+        let mut edges = Vec::with_capacity(children.len() * EDGE_LEN);
+        for (index, children) in children.into_iter().enumerate() {
+            nodes.extend([
+                index as u32,
+                (edges.len() / EDGE_LEN) as u32,
+                children.len() as u32,
+            ]);
+            for (distance, child) in children {
+                let bits = distance.to_bits();
+                edges.extend([child, bits as u32, (bits >> 32) as u32]);
+            }
+        }
+
+        Self {
+            nodes: nodes.into(),
+            edges: edges.into(),
+        }
+    }
+
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        out.table(&self.nodes, NODE_LEN);
+        out.table(&self.edges, EDGE_LEN);
+    }
+
+    /// Decodes a tree, checking that every node refers to a corpus string
+    /// and that children always follow their parent, so the tree has no
+    /// cycles.
+    pub(crate) fn decode(input: &mut Decoder<'_>, keys: &Corpus) -> Result<Self, &'static str> {
+        const MALFORMED: &str = "index file holds a malformed BK-tree";
+
+        let tree = Self {
+            nodes: input.table(NODE_LEN)?,
+            edges: input.table(EDGE_LEN)?,
+        };
+        check_key_indexes(&tree.nodes, NODE_LEN, 0, keys)?;
+
+        let len = tree.len();
+        let mut next_edge = 0;
+        for node in 0..len {
+            let edges = tree.edge_range(node);
+            if edges.start != next_edge || edges.end > tree.edges.len() / EDGE_LEN {
+                return Err(MALFORMED);
+            }
+            next_edge = edges.end;
+
+            for edge in edges {
+                let child = tree.edges.get(edge * EDGE_LEN) as usize;
+                if child <= node || child >= len {
+                    return Err(MALFORMED);
+                }
+            }
+        }
+        if next_edge != tree.edges.len() / EDGE_LEN {
+            return Err(MALFORMED);
+        }
+
+        Ok(tree)
+    }
+
+    /// The number of nodes in the tree.
+    fn len(&self) -> usize {
+        self.nodes.len() / NODE_LEN
+    }
+
+    /// The corpus index of the key stored in `node`.
+    fn key(&self, node: usize) -> usize {
+        self.nodes.get(node * NODE_LEN) as usize
+    }
+
+    /// The positions of the edges from `node` to its children.
+    fn edge_range(&self, node: usize) -> std::ops::Range<usize> {
+        let first = self.nodes.get(node * NODE_LEN + 1) as usize;
+        first..first + self.nodes.get(node * NODE_LEN + 2) as usize
+    }
+
+    /// The children of `node`, labelled with their distance from it.
+    fn children(&self, node: usize) -> impl Iterator<Item = (f64, usize)> + '_ {
+        self.edge_range(node).map(|edge| {
+            let position = edge * EDGE_LEN;
+            let distance = self.edges.get_f64(position + 1);
+            (distance, self.edges.get(position) as usize)
+        })
+    }
+
+    /// Finds every key within `max_distance` of `arg`, sorted from best to
+    /// worst match.
+    pub(crate) fn within_distance(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        max_distance: f64,
+    ) -> Vec<Candidate> {
+        let mut matches = Vec::new();
+        let mut stack: Vec<usize> = if self.len() == 0 { vec![] } else { vec![0] };
+
+        while let Some(current) = stack.pop() {
+            let index = self.key(current);
+            let distance = metric.distance(arg, &keys[index]);
+
+            if distance <= max_distance {
+                matches.push(Candidate {
+                    index,
+                    distance,
+                    score: metric.similarity_from_distance(arg, &keys[index], distance),
+                });
+            }
+
+            stack.extend(
+                self.children(current)
+                    .filter(|(edge, _)| (edge - distance).abs() <= max_distance)
+                    .map(|(_, child)| child),
+            );
+        }
+
+        matches.sort_unstable();
+        matches
+    }
+
+    /// Finds the `k` keys closest to `arg`, sorted from best to worst match.
+    ///
+    /// The search radius starts unbounded and shrinks to the distance of the
+    /// worst of the best `k` candidates found so far.
+    pub(crate) fn top_k(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        k: usize,
+    ) -> Vec<Candidate> {
+        if k == 0 || self.len() == 0 {
+            return Vec::new();
+        }
+
+        let mut best: BinaryHeap<Candidate> = BinaryHeap::with_capacity(k + 1);
+        let mut stack = vec![0];
+
+        while let Some(current) = stack.pop() {
+            let index = self.key(current);
+            let distance = metric.distance(arg, &keys[index]);
+
+            let radius = match best.peek() {
+                Some(worst) if best.len() == k => worst.distance,
+                _ => f64::INFINITY,
+            };
+
+            if distance <= radius {
+                best.push(Candidate {
+                    index,
+                    distance,
+                    score: metric.similarity_from_distance(arg, &keys[index], distance),
+                });
+
+                if best.len() > k {
+                    best.pop();
+                }
+            }
+
+            let radius = match best.peek() {
+                Some(worst) if best.len() == k => worst.distance,
+                _ => f64::INFINITY,
+            };
+
+            // Visit the children closest to the query's distance first, as
+            // they are the most likely to shrink the radius.
+            let mut children: Vec<(f64, usize)> = self
+                .children(current)
+                .filter(|(edge, _)| (edge - distance).abs() <= radius)
+                .collect();
+            children.sort_unstable_by(|(a, _), (b, _)| {
+                (b - distance).abs().total_cmp(&(a - distance).abs())
+            });
+            stack.extend(children.into_iter().map(|(_, child)| child));
+        }
+
+        best.into_sorted_vec()
+    }
+}
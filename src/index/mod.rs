@@ -0,0 +1,439 @@
+//! Index structures that answer searches without scanning the whole corpus.
+//!
+//! An index is built over the searcher's (normalized) corpus when it is
+//! configured with [`FuzzySearcher::with_index`](crate::FuzzySearcher::with_index).
+//! Every index returns exactly the same candidates, in the same order, as the
+//! linear scan it replaces. Searches that an index cannot answer exactly
+//! return `None`, and the searcher falls back to a linear scan.
+
+mod automaton;
+mod bk_tree;
+mod ngram;
+mod persist;
+mod sym_spell;
+
+use crate::{Candidate, Corpus, FuzzySearchError, Metric};
+
+use automaton::Trie;
+use bk_tree::BkTree;
+use ngram::NGramIndex;
+use persist::{Decoder, Encoder, Words, check_key_indexes};
+
+pub(crate) use persist::IndexBytes;
+use sym_spell::SymSpell;
+
+/// The kind of index a [`FuzzySearcher`](crate::FuzzySearcher) can build.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum IndexKind {
+    /// A Burkhard-Keller tree, which prunes the corpus using the triangle
+    /// inequality. Requires a metric for which
+    /// [`Metric::satisfies_triangle_inequality`] returns `true`, such as
+    /// [`Levenshtein`](crate::Levenshtein).
+    BkTree,
+
+    /// A SymSpell-style symmetric-delete index, which precomputes every
+    /// string reachable by deleting up to `max_distance` characters from the
+    /// first `prefix_length` characters of each corpus string.
+    ///
+    /// Lookups within `max_distance` edits take microseconds, but the index
+    /// grows quickly with `max_distance`, so it suits distances of 1 or 2.
+    /// Searches for more distant matches fall back to a linear scan. A
+    /// `prefix_length` of around 7 keeps the index small without producing
+    /// too many candidates, and it must be greater than `max_distance`.
+    /// Requires a metric for which [`Metric::bounded_by_deletions`] returns
+    /// `true`.
+    SymSpell {
+        /// The largest number of edits the index finds matches within.
+        max_distance: usize,
+        /// The number of leading characters of each string that is indexed,
+        /// which must be greater than `max_distance`.
+        prefix_length: usize,
+    },
+
+    /// A trie of the sorted corpus, searched with a Levenshtein automaton
+    /// compiled from each query.
+    ///
+    /// The automaton skips every key below a trie node as soon as no
+    /// extension of that node's prefix can be within the maximum distance,
+    /// so keys sharing a prefix are compared together. Requires a metric for
+    /// which [`Metric::bounded_by_levenshtein`] returns `true`.
+    LevenshteinAutomaton,
+
+    /// An inverted index from the overlapping `gram_size`-character grams of
+    /// each corpus string to the strings containing them. Only strings
+    /// sharing enough grams with the query are compared against it, which
+    /// suits long strings such as product titles and addresses.
+    ///
+    /// Top-k searches only compare strings sharing at least `min_overlap`
+    /// (between 0 and 1) of the query's grams, falling back to a linear scan
+    /// if the filter could have missed a match. Requires a metric for which
+    /// [`Metric::bounded_by_levenshtein`] returns `true`.
+    NGram {
+        /// The number of characters in each gram, usually 2 or 3.
+        gram_size: usize,
+        /// The fraction of the query's grams a string must share.
+        min_overlap: f64,
+    },
+}
+
+/// An inverted index from 32-bit hashes to the corpus indexes of the keys
+/// they were generated from, stored as a single sorted table.
+#[derive(Debug)]
+struct Postings {
+    /// `(hash, corpus index)` pairs, two words each, sorted and
+    /// deduplicated.
+    entries: Words,
+}
+
+impl Postings {
+    fn new(mut entries: Vec<(u32, u32)>) -> Self {
+        entries.sort_unstable();
+        entries.dedup();
+
+        Self {
+            entries: entries
+                .into_iter()
+                .flat_map(|(hash, index)| [hash, index])
+                .collect::<Vec<_>>()
+                .into(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len() / 2
+    }
+
+    fn entry(&self, position: usize) -> (u32, u32) {
+        (
+            self.entries.get(2 * position),
+            self.entries.get(2 * position + 1),
+        )
+    }
+
+    /// Returns the corpus indexes of the keys posted under `hash`, in
+    /// ascending order.
+    fn get(&self, hash: u32) -> impl Iterator<Item = usize> + '_ {
+        let (mut start, mut end) = (0, self.len());
+        while start < end {
+            let middle = start + (end - start) / 2;
+            if self.entry(middle).0 < hash {
+                start = middle + 1;
+            } else {
+                end = middle;
+            }
+        }
+
+        (start..self.len())
+            .map(|position| self.entry(position))
+            .take_while(move |&(other, _)| other == hash)
+            .map(|(_, index)| index as usize)
+    }
+
+    /// Returns `true` if the entries are sorted without duplicates.
+    fn is_sorted(&self) -> bool {
+        let mut words = self.entries.iter();
+        let mut last = None;
+        while let (Some(hash), Some(index)) = (words.next(), words.next()) {
+            if last >= Some((hash, index)) {
+                return false;
+            }
+            last = Some((hash, index));
+        }
+        true
+    }
+
+    fn encode(&self, out: &mut Encoder) {
+        out.table(&self.entries, 2);
+    }
+
+    fn decode(input: &mut Decoder<'_>, keys: &Corpus) -> Result<Self, &'static str> {
+        let postings = Self {
+            entries: input.table(2)?,
+        };
+        check_key_indexes(&postings.entries, 2, 1, keys)?;
+        if !postings.is_sorted() {
+            return Err("index file postings are not sorted");
+        }
+
+        Ok(postings)
+    }
+}
+
+/// Hashes a sequence of characters with 32-bit FNV-1a.
+///
+/// The hash is stable across platforms and releases, unlike the standard
+/// library's hashers.
+fn hash_chars(chars: &[char]) -> u32 {
+    const OFFSET_BASIS: u32 = 0x811c_9dc5;
+    const PRIME: u32 = 0x0100_0193;
+
+    let mut hash = OFFSET_BASIS;
+    for &c in chars {
+        for byte in (c as u32).to_le_bytes() {
+            hash ^= u32::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+
+    hash
+}
+
+/// Checks that an index of the given kind returns correct results with the
+/// given metric, returning the reason if it does not.
+fn check_compatible(kind: IndexKind, metric: &dyn Metric) -> Result<(), &'static str> {
+    match kind {
+        IndexKind::BkTree => {
+            if !metric.satisfies_triangle_inequality() {
+                return Err("BK-trees require a metric that satisfies the triangle inequality");
+            }
+        }
+        IndexKind::SymSpell {
+            max_distance,
+            prefix_length,
+        } => {
+            if !metric.bounded_by_deletions() {
+                return Err("SymSpell indexes require a metric that is bounded by deletions");
+            }
+            if prefix_length <= max_distance {
+                return Err("SymSpell prefix length must be greater than the maximum distance");
+            }
+        }
+        IndexKind::LevenshteinAutomaton => {
+            if !metric.bounded_by_levenshtein() {
+                return Err(
+                    "Levenshtein automata require a metric that is bounded by Levenshtein distance",
+                );
+            }
+        }
+        IndexKind::NGram {
+            gram_size,
+            min_overlap,
+        } => {
+            if !metric.bounded_by_levenshtein() {
+                return Err(
+                    "n-gram indexes require a metric that is bounded by Levenshtein distance",
+                );
+            }
+            if gram_size == 0 {
+                return Err("n-gram size must be at least 1");
+            }
+            if !(0.0..=1.0).contains(&min_overlap) {
+                return Err("n-gram minimum overlap must be between 0 and 1");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A built index over a corpus.
+#[derive(Debug)]
+pub(crate) enum Index {
+    BkTree(BkTree),
+    SymSpell(SymSpell),
+    Trie(Trie),
+    NGram(NGramIndex),
+}
+
+impl Index {
+    /// Builds an index of the given kind over `keys`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::IncompatibleIndex` if the index cannot
+    /// return correct results with the given metric.
+    pub(crate) fn build(
+        kind: IndexKind,
+        keys: &Corpus,
+        metric: &dyn Metric,
+    ) -> Result<Self, FuzzySearchError> {
+        check_compatible(kind, metric)
+            .map_err(|reason| FuzzySearchError::IncompatibleIndex { reason })?;
+
+        Ok(match kind {
+            IndexKind::BkTree => Index::BkTree(BkTree::build(keys, metric)),
+            IndexKind::SymSpell {
+                max_distance,
+                prefix_length,
+            } => Index::SymSpell(SymSpell::build(keys, max_distance, prefix_length)),
+            IndexKind::LevenshteinAutomaton => Index::Trie(Trie::build(keys)),
+            IndexKind::NGram {
+                gram_size,
+                min_overlap,
+            } => Index::NGram(NGramIndex::build(keys, gram_size, min_overlap)),
+        })
+    }
+
+    /// Returns the kind of this index.
+    pub(crate) fn kind(&self) -> IndexKind {
+        match self {
+            Index::BkTree(_) => IndexKind::BkTree,
+            Index::SymSpell(index) => IndexKind::SymSpell {
+                max_distance: index.max_distance(),
+                prefix_length: index.prefix_length(),
+            },
+            Index::Trie(_) => IndexKind::LevenshteinAutomaton,
+            Index::NGram(index) => IndexKind::NGram {
+                gram_size: index.gram_size(),
+                min_overlap: index.min_overlap(),
+            },
+        }
+    }
+
+    /// Finds the `k` keys closest to `arg`, sorted from best to worst match.
+    pub(crate) fn top_k(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        k: usize,
+    ) -> Option<Vec<Candidate>> {
+        match self {
+            Index::BkTree(tree) => Some(tree.top_k(arg, keys, metric, k)),
+            Index::SymSpell(index) => index.top_k(arg, keys, metric, k),
+            Index::Trie(trie) => trie.top_k(arg, keys, metric, k),
+            Index::NGram(index) => index.top_k(arg, keys, metric, k),
+        }
+    }
+
+    /// Finds every key within `max_distance` of `arg`, sorted from best to
+    /// worst match.
+    pub(crate) fn within_distance(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        max_distance: f64,
+    ) -> Option<Vec<Candidate>> {
+        match self {
+            Index::BkTree(tree) => Some(tree.within_distance(arg, keys, metric, max_distance)),
+            Index::SymSpell(index) => (max_distance <= index.max_distance() as f64)
+                .then(|| index.within_distance(arg, keys, metric, max_distance)),
+            Index::Trie(trie) => Some(trie.within_distance(arg, keys, metric, max_distance)),
+            Index::NGram(index) => index.within_distance(arg, keys, metric, max_distance),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DamerauLevenshtein, Hamming, Levenshtein, OptimalStringAlignment, Positional, Threshold,
+        find_top_k, find_within,
+    };
+
+    /// Close variants of a few words, with a duplicate, the empty string, a
+    /// non-ASCII word and a single character.
+    pub(super) fn corpus() -> Corpus {
+        [
+            "hello",
+            "help",
+            "yellow",
+            "hell",
+            "shell",
+            "helo",
+            "world",
+            "word",
+            "hello",
+            "",
+            "caf\u{e9}",
+            "h",
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// Generates short words over a small alphabet, so that many of them are
+    /// within a few edits of each other.
+    pub(super) fn words(count: usize) -> Corpus {
+        let mut state: u32 = 0x1234_5678;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        (0..count)
+            .map(|_| {
+                let len = next() % 10;
+                (0..len)
+                    .map(|_| (b'a' + (next() % 4) as u8) as char)
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn indexes_match_linear_scans_whenever_they_answer() {
+        let kinds = [
+            IndexKind::BkTree,
+            IndexKind::SymSpell {
+                max_distance: 2,
+                prefix_length: 3,
+            },
+            IndexKind::SymSpell {
+                max_distance: 2,
+                prefix_length: 7,
+            },
+            IndexKind::LevenshteinAutomaton,
+            IndexKind::NGram {
+                gram_size: 2,
+                min_overlap: 0.25,
+            },
+            IndexKind::NGram {
+                gram_size: 3,
+                min_overlap: 0.5,
+            },
+        ];
+        let metrics = [
+            &Levenshtein::new() as &dyn Metric,
+            &OptimalStringAlignment::new(),
+            &DamerauLevenshtein::new(),
+            &Hamming::new(),
+            &Positional::new(),
+        ];
+        let queries = ["helo", "wordl", "x", "shelf", "cafe", "", "abca", "dcb"];
+
+        for keys in [corpus(), words(300)] {
+            for kind in kinds {
+                for metric in metrics {
+                    // Skip the metrics that the kind of index cannot serve.
+                    let Ok(index) = Index::build(kind, &keys, metric) else {
+                        continue;
+                    };
+
+                    for query in queries {
+                        for max_distance in [0.0, 1.0, 1.5, 2.0, 5.0] {
+                            if let Some(matches) =
+                                index.within_distance(query, &keys, metric, max_distance)
+                            {
+                                assert_eq!(
+                                    matches,
+                                    find_within(
+                                        query,
+                                        &keys,
+                                        metric,
+                                        Threshold::MaxDistance(max_distance)
+                                    ),
+                                    "{kind:?}, query {query:?}, max distance {max_distance}"
+                                );
+                            }
+                        }
+                        for k in [0, 1, 3, 20] {
+                            if let Some(top_k) = index.top_k(query, &keys, metric, k) {
+                                assert_eq!(
+                                    top_k,
+                                    find_top_k(query, &keys, metric, k),
+                                    "{kind:?}, query {query:?}, k {k}"
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
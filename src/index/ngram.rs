@@ -0,0 +1,257 @@
+//! A q-gram inverted index with candidate filtering.
+//!
+//! Each key is padded at both ends and split into overlapping grams of
+//! `gram_size` characters, and the index maps every gram to the keys that
+//! contain it. A search counts how many of the query's distinct grams each
+//! key shares, and only verifies keys that share enough of them with the
+//! searcher's metric. Unlike tree-based indexes, the work per query depends
+//! on how many keys share grams with it rather than on the string lengths,
+//! so it suits long strings such as product titles and addresses.
+//!
+//! A single edit changes at most `gram_size` grams (the q-gram lemma), so a
+//! key within `d` edits of a query shares at least `grams - d * gram_size`
+//! of the query's distinct grams. Since the searcher's metric is bounded by
+//! Levenshtein distance, this gives an exact filter for distance-bounded
+//! searches, and lets top-k searches detect when the `min_overlap` filter may
+//! have missed a match.
+
+use std::collections::BinaryHeap;
+
+use super::{Decoder, Encoder, Postings, hash_chars};
+use crate::{Candidate, Corpus, Metric};
+
+/// Pads both ends of every string, so that each character, including the
+/// first and last, appears in `gram_size` grams.
+const PADDING: char = '\u{0}';
+
+#[derive(Debug)]
+pub(crate) struct NGramIndex {
+    gram_size: usize,
+    min_overlap: f64,
+    /// The keys each gram appears in.
+    grams: Postings,
+}
+
+impl NGramIndex {
+    /// Builds an index of the grams of every key.
+    pub(crate) fn build(keys: &Corpus, gram_size: usize, min_overlap: f64) -> Self {
+        let mut grams = Vec::new();
+        let mut key_grams = Vec::new();
+
+        for (index, key) in keys.iter().enumerate() {
+            key_grams.clear();
+            collect_grams(key, gram_size, &mut key_grams);
+
+            let index = u32::try_from(index).expect("corpus is too large for an n-gram index");
+            grams.extend(key_grams.iter().map(|&hash| (hash, index)));
+        }
+
+        Self {
+            gram_size,
+            min_overlap,
+            grams: Postings::new(grams),
+        }
+    }
+
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        self.grams.encode(out);
+    }
+
+    pub(crate) fn decode(
+        input: &mut Decoder<'_>,
+        keys: &Corpus,
+        gram_size: usize,
+        min_overlap: f64,
+    ) -> Result<Self, &'static str> {
+        Ok(Self {
+            gram_size,
+            min_overlap,
+            grams: Postings::decode(input, keys)?,
+        })
+    }
+
+    /// The number of characters in each gram.
+    pub(crate) fn gram_size(&self) -> usize {
+        self.gram_size
+    }
+
+    /// The fraction of the query's grams a key must share to be verified by
+    /// top-k searches.
+    pub(crate) fn min_overlap(&self) -> f64 {
+        self.min_overlap
+    }
+
+    /// Finds every key within `max_distance` of `arg`, sorted from best to
+    /// worst match.
+    ///
+    /// Returns `None` if the distance is too large for the q-gram lemma to
+    /// rule out any key.
+    pub(crate) fn within_distance(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        max_distance: f64,
+    ) -> Option<Vec<Candidate>> {
+        let (arg_grams, counts) = self.shared_gram_counts(arg);
+
+        let min_shared = self.lemma_bound(arg_grams, max_distance)?;
+
+        let mut matches: Vec<Candidate> = counts
+            .into_iter()
+            .filter(|&(_, shared)| shared >= min_shared)
+            .filter_map(|(index, _)| {
+                Candidate::within(arg, &keys[index], index, metric, max_distance)
+            })
+            .collect();
+
+        matches.sort_unstable();
+        Some(matches)
+    }
+
+    /// Finds the `k` best keys among those sharing at least `min_overlap` of
+    /// the query's grams, sorted from best to worst match.
+    ///
+    /// Returns `None` if a key that was filtered out could still be among the
+    /// best `k`.
+    pub(crate) fn top_k(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        k: usize,
+    ) -> Option<Vec<Candidate>> {
+        if k == 0 {
+            return Some(Vec::new());
+        }
+
+        let (arg_grams, counts) = self.shared_gram_counts(arg);
+        let min_shared = self.overlap_bound(arg_grams);
+
+        let mut best: BinaryHeap<Candidate> = BinaryHeap::with_capacity(k + 1);
+        for (index, shared) in counts {
+            if shared < min_shared {
+                continue;
+            }
+
+            best.push(Candidate::new(arg, &keys[index], index, metric));
+            if best.len() > k {
+                best.pop();
+            }
+        }
+
+        // Every key within the worst kept distance must have shared enough
+        // grams to have been considered.
+        let worst = match best.peek() {
+            Some(worst) if best.len() == k => worst.distance,
+            _ => f64::INFINITY,
+        };
+        match self.lemma_bound(arg_grams, worst) {
+            Some(lemma_shared) if lemma_shared >= min_shared => {}
+            _ => return None,
+        }
+
+        Some(best.into_sorted_vec())
+    }
+
+    /// Returns the number of distinct grams in `arg`, and the corpus index
+    /// of every key sharing at least one of them with the number it shares.
+    fn shared_gram_counts(&self, arg: &str) -> (usize, Vec<(usize, usize)>) {
+        let mut arg_grams = Vec::new();
+        collect_grams(arg, self.gram_size, &mut arg_grams);
+
+        let mut postings: Vec<usize> = arg_grams
+            .iter()
+            .flat_map(|&hash| self.grams.get(hash))
+            .collect();
+        postings.sort_unstable();
+
+        let mut counts: Vec<(usize, usize)> = Vec::new();
+        for index in postings {
+            match counts.last_mut() {
+                Some((last, shared)) if *last == index => *shared += 1,
+                _ => counts.push((index, 1)),
+            }
+        }
+
+        (arg_grams.len(), counts)
+    }
+
+    /// The number of the query's distinct grams that every key within
+    /// `max_distance` edits must share, or `None` if it is not positive.
+    fn lemma_bound(&self, arg_grams: usize, max_distance: f64) -> Option<usize> {
+        if max_distance < 0.0 {
+            return Some(usize::MAX);
+        }
+
+        let lost = (max_distance.floor() * self.gram_size as f64).min(usize::MAX as f64) as usize;
+        arg_grams.checked_sub(lost).filter(|&shared| shared > 0)
+    }
+
+    /// The number of the query's distinct grams that `min_overlap` requires
+    /// a key to share, which is always at least one.
+    fn overlap_bound(&self, arg_grams: usize) -> usize {
+        ((arg_grams as f64 * self.min_overlap).ceil() as usize).max(1)
+    }
+}
+
+/// Pushes the hash of every distinct gram of the padded `text`.
+fn collect_grams(text: &str, gram_size: usize, out: &mut Vec<u32>) {
+    let padding = std::iter::repeat_n(PADDING, gram_size - 1);
+    let chars: Vec<char> = padding.clone().chain(text.chars()).chain(padding).collect();
+
+    out.extend(chars.windows(gram_size).map(hash_chars));
+    out.sort_unstable();
+    out.dedup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Levenshtein, Threshold, find_top_k, find_within};
+
+    /// Addresses and product titles, long enough for the q-gram lemma to
+    /// bound how many grams a close key shares.
+    fn long_strings() -> Corpus {
+        [
+            "123 Main Street, Springfield",
+            "123 Main St, Springfield",
+            "321 Main Street, Shelbyville",
+            "Apple iPhone 15 Pro Max 256GB",
+            "Apple iPhone 15 Pro 128GB",
+            "Samsung Galaxy S24 Ultra 512GB",
+            "",
+            "x",
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn lemma_bound_decides_which_searches_are_answered() {
+        let keys = long_strings();
+        let metric = Levenshtein::new();
+        let index = NGramIndex::build(&keys, 3, 0.3);
+        let query = "123 Main Stret, Springfeld";
+
+        // A few edits leave most of a long query's grams intact, but enough
+        // edits could change all of them.
+        assert_eq!(
+            index.within_distance(query, &keys, &metric, 5.0),
+            Some(find_within(
+                query,
+                &keys,
+                &metric,
+                Threshold::MaxDistance(5.0)
+            ))
+        );
+        assert_eq!(index.within_distance(query, &keys, &metric, 100.0), None);
+
+        // The closest key is two edits away, so any key sharing fewer grams
+        // is further away than it.
+        assert_eq!(
+            index.top_k(query, &keys, &metric, 1),
+            Some(find_top_k(query, &keys, &metric, 1))
+        );
+    }
+}
@@ -0,0 +1,576 @@
+//! Reading and writing index files, in the versioned binary format
+//! documented on [`FuzzySearcher::save_index`](crate::FuzzySearcher::save_index).
+//!
+//! Tables are read in place from the file's bytes rather than decoded, so a
+//! mapped index file shares its pages with every process that maps it.
+//! Loading only checks that the tables are consistent with each other and
+//! with the corpus.
+
+use std::{fmt, ops::Deref, sync::Arc};
+
+use memmap2::Mmap;
+
+use super::{
+    Index, IndexKind, automaton::Trie, bk_tree::BkTree, check_compatible, ngram::NGramIndex,
+    sym_spell::SymSpell,
+};
+use crate::{Corpus, Metric};
+
+const MAGIC: &[u8; 8] = b"FZSINDEX";
+const VERSION: u32 = 2;
+const HEADER_LEN: usize = 48;
+
+/// Strings whose pairwise distances identify a metric.
+const METRIC_PROBES: &[&str] = &[
+    "",
+    "a",
+    "ab",
+    "ba",
+    "abc",
+    "kitten",
+    "sitting",
+    "martha",
+    "marhta",
+    "Hello",
+    "hello",
+    "caf\u{e9}",
+    "cafe\u{301}",
+];
+
+/// The hash of index file fingerprints and checksums: a variant of 64-bit
+/// FNV-1a that mixes in whole 8-byte words rather than single bytes. It is
+/// stable across platforms and releases, but is not FNV-1a itself.
+struct WordHash(u64);
+
+impl WordHash {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Hashes each 8-byte word of `bytes` as a single `u64`, then any
+    /// remaining bytes one at a time, so that index payloads of hundreds of
+    /// megabytes are checked quickly.
+    fn write(&mut self, bytes: &[u8]) {
+        let (words, rest) = bytes.as_chunks::<8>();
+        for &word in words {
+            self.write_u64(u64::from_le_bytes(word));
+        }
+        for &byte in rest {
+            self.write_u64(u64::from(byte));
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 ^= value;
+        self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+    }
+}
+
+fn corpus_fingerprint(keys: &Corpus) -> u64 {
+    let mut hasher = WordHash::new();
+    hasher.write_u64(keys.len() as u64);
+    for key in keys {
+        hasher.write_u64(key.len() as u64);
+        hasher.write(key.as_bytes());
+    }
+    hasher.0
+}
+
+fn metric_fingerprint(metric: &dyn Metric) -> u64 {
+    let mut hasher = WordHash::new();
+    for a in METRIC_PROBES {
+        for b in METRIC_PROBES {
+            hasher.write_u64(metric.distance(a, b).to_bits());
+        }
+    }
+    hasher.0
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = WordHash::new();
+    hasher.write(bytes);
+    hasher.0
+}
+
+/// The bytes of an index file, either read into memory or memory-mapped.
+pub(crate) enum IndexBytes {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for IndexBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            IndexBytes::Read(bytes) => bytes,
+            IndexBytes::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// A read-only array of `u32` words, either built in memory or read in place
+/// from a table of an index file.
+pub(crate) enum Words {
+    Built(Vec<u32>),
+    /// `len` little-endian words starting at byte `start` of the file.
+    File {
+        bytes: Arc<IndexBytes>,
+        start: usize,
+        len: usize,
+    },
+}
+
+impl Words {
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Words::Built(words) => words.len(),
+            Words::File { len, .. } => *len,
+        }
+    }
+
+    /// Returns the word at `position`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` is out of bounds.
+    pub(crate) fn get(&self, position: usize) -> u32 {
+        match self {
+            Words::Built(words) => words[position],
+            Words::File { bytes, start, len } => {
+                assert!(position < *len, "word {position} is out of bounds");
+                let offset = start + position * 4;
+                u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+            }
+        }
+    }
+
+    /// Returns the `f64` whose bits are stored in the two words at
+    /// `position`, low half first.
+    pub(crate) fn get_f64(&self, position: usize) -> f64 {
+        let low = u64::from(self.get(position));
+        let high = u64::from(self.get(position + 1));
+        f64::from_bits(high << 32 | low)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        // Exactly one of these is empty.
+        let (built, file): (&[u32], &[[u8; 4]]) = match self {
+            Words::Built(words) => (words, &[]),
+            Words::File { bytes, start, len } => {
+                (&[], bytes[*start..start + len * 4].as_chunks().0)
+            }
+        };
+
+        built
+            .iter()
+            .copied()
+            .chain(file.iter().map(|&word| u32::from_le_bytes(word)))
+    }
+}
+
+impl From<Vec<u32>> for Words {
+    fn from(words: Vec<u32>) -> Self {
+        Words::Built(words)
+    }
+}
+
+impl fmt::Debug for Words {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Appends the fields of an index file to a buffer.
+pub(crate) struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub(crate) fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+
+    /// Appends a table of entries, each `entry_len` words long.
+    pub(crate) fn table(&mut self, words: &Words, entry_len: usize) {
+        self.usize(words.len() / entry_len);
+        self.bytes.reserve(words.len() * 4);
+        for word in words.iter() {
+            self.bytes.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+}
+
+/// Reads the fields of an index file, failing with a description of the
+/// problem if the file ends early or holds impossible values.
+pub(crate) struct Decoder<'a> {
+    bytes: &'a Arc<IndexBytes>,
+    /// The position of the next field.
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Returns the bytes after the next field.
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        let field = self
+            .rest()
+            .first_chunk::<N>()
+            .ok_or("index file is truncated")?;
+        self.position += N;
+        Ok(*field)
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, &'static str> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn usize(&mut self) -> Result<usize, &'static str> {
+        usize::try_from(self.u64()?).map_err(|_| "index file holds an oversized value")
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64, &'static str> {
+        self.u64().map(f64::from_bits)
+    }
+
+    /// Reads a table of entries, each `entry_len` words long, checking that
+    /// the file is long enough to hold them. The words are not copied.
+    pub(crate) fn table(&mut self, entry_len: usize) -> Result<Words, &'static str> {
+        let len = self.usize()?.saturating_mul(entry_len);
+        if len.saturating_mul(4) > self.rest().len() {
+            return Err("index file is truncated");
+        }
+
+        let words = Words::File {
+            bytes: Arc::clone(self.bytes),
+            start: self.position,
+            len,
+        };
+        self.position += len * 4;
+        Ok(words)
+    }
+}
+
+/// Checks that every corpus index in a table is within the corpus.
+///
+/// The indexes are the words at `offset` within each `entry_len`-word entry.
+pub(crate) fn check_key_indexes(
+    words: &Words,
+    entry_len: usize,
+    offset: usize,
+    keys: &Corpus,
+) -> Result<(), &'static str> {
+    if words
+        .iter()
+        .skip(offset)
+        .step_by(entry_len)
+        .any(|index| index as usize >= keys.len())
+    {
+        return Err("index file refers to a string beyond the corpus");
+    }
+    Ok(())
+}
+
+impl Index {
+    /// Encodes the index in the index file format.
+    pub(crate) fn to_bytes(&self, keys: &Corpus, metric: &dyn Metric) -> Vec<u8> {
+        let mut payload = Encoder { bytes: Vec::new() };
+        let kind = match self {
+            Index::BkTree(tree) => {
+                tree.encode(&mut payload);
+                0
+            }
+            Index::SymSpell(index) => {
+                payload.usize(index.max_distance());
+                payload.usize(index.prefix_length());
+                index.encode(&mut payload);
+                1
+            }
+            Index::Trie(trie) => {
+                trie.encode(&mut payload);
+                2
+            }
+            Index::NGram(index) => {
+                payload.usize(index.gram_size());
+                payload.f64(index.min_overlap());
+                index.encode(&mut payload);
+                3
+            }
+        };
+        let payload = payload.bytes;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[kind, 0, 0, 0]);
+        bytes.extend_from_slice(&corpus_fingerprint(keys).to_le_bytes());
+        bytes.extend_from_slice(&metric_fingerprint(metric).to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&checksum(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        bytes
+    }
+
+    /// Loads an index saved by [`Index::to_bytes`] for the given corpus and
+    /// metric.
+    ///
+    /// The index reads its tables in place from `bytes`, after checking that
+    /// they are consistent with each other and with the corpus.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the file is not an index
+    /// file, is in an unsupported version, was saved for a different corpus
+    /// or metric, or is corrupted.
+    pub(crate) fn from_bytes(
+        bytes: IndexBytes,
+        keys: &Corpus,
+        metric: &dyn Metric,
+    ) -> Result<Self, &'static str> {
+        let bytes = Arc::new(bytes);
+        let mut input = Decoder {
+            bytes: &bytes,
+            position: 0,
+        };
+
+        if input.take::<8>().ok() != Some(*MAGIC) {
+            return Err("file is not an index file");
+        }
+        if input.u32()? != VERSION {
+            return Err("index file version is not supported");
+        }
+        let [kind, reserved @ ..] = input.take::<4>()?;
+        if reserved != [0; 3] {
+            return Err("index file has reserved header bytes set");
+        }
+        if input.u64()? != corpus_fingerprint(keys) {
+            return Err("index was saved for a different corpus");
+        }
+        if input.u64()? != metric_fingerprint(metric) {
+            return Err("index was saved for a different metric");
+        }
+        let payload_len = input.usize()?;
+        let expected_checksum = input.u64()?;
+
+        let payload = input.rest();
+        if payload.len() != payload_len {
+            return Err("index file is truncated or has trailing bytes");
+        }
+        if checksum(payload) != expected_checksum {
+            return Err("index file checksum does not match");
+        }
+
+        let index = match kind {
+            0 => {
+                check_compatible(IndexKind::BkTree, metric)?;
+                Index::BkTree(BkTree::decode(&mut input, keys)?)
+            }
+            1 => {
+                let max_distance = input.usize()?;
+                let prefix_length = input.usize()?;
+                let kind = IndexKind::SymSpell {
+                    max_distance,
+                    prefix_length,
+                };
+                check_compatible(kind, metric)?;
+                Index::SymSpell(SymSpell::decode(
+                    &mut input,
+                    keys,
+                    max_distance,
+                    prefix_length,
+                )?)
+            }
+            2 => {
+                check_compatible(IndexKind::LevenshteinAutomaton, metric)?;
+                Index::Trie(Trie::decode(&mut input, keys)?)
+            }
+            3 => {
+                let gram_size = input.usize()?;
+                let min_overlap = input.f64()?;
+                let kind = IndexKind::NGram {
+                    gram_size,
+                    min_overlap,
+                };
+                check_compatible(kind, metric)?;
+                Index::NGram(NGramIndex::decode(
+                    &mut input,
+                    keys,
+                    gram_size,
+                    min_overlap,
+                )?)
+            }
+            _ => return Err("index file holds an unknown kind of index"),
+        };
+
+        if !input.rest().is_empty() {
+            return Err("index file has trailing bytes");
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::corpus;
+    use crate::{DamerauLevenshtein, Levenshtein, find_top_k};
+
+    fn read(bytes: &[u8]) -> IndexBytes {
+        IndexBytes::Read(bytes.to_vec())
+    }
+
+    const KINDS: &[IndexKind] = &[
+        IndexKind::BkTree,
+        IndexKind::SymSpell {
+            max_distance: 2,
+            prefix_length: 5,
+        },
+        IndexKind::LevenshteinAutomaton,
+        IndexKind::NGram {
+            gram_size: 2,
+            min_overlap: 0.25,
+        },
+    ];
+
+    #[test]
+    fn loaded_indexes_match_built_indexes() {
+        let keys = corpus();
+        let metric = Levenshtein::new();
+
+        for &kind in KINDS {
+            let built = Index::build(kind, &keys, &metric).unwrap();
+            let bytes = IndexBytes::Read(built.to_bytes(&keys, &metric));
+            let loaded = Index::from_bytes(bytes, &keys, &metric).unwrap();
+
+            assert_eq!(loaded.kind(), kind);
+            for query in ["helo", "wrld", "cafe", "x"] {
+                assert_eq!(
+                    loaded.top_k(query, &keys, &metric, 3),
+                    built.top_k(query, &keys, &metric, 3)
+                );
+                assert_eq!(
+                    loaded.within_distance(query, &keys, &metric, 1.0),
+                    built.within_distance(query, &keys, &metric, 1.0)
+                );
+            }
+            if let Some(top_k) = loaded.top_k("helo", &keys, &metric, 3) {
+                assert_eq!(top_k, find_top_k("helo", &keys, &metric, 3));
+            }
+        }
+    }
+
+    #[test]
+    fn stale_indexes_are_rejected() {
+        let keys = corpus();
+        let metric = Levenshtein::new();
+        let bytes = Index::build(IndexKind::BkTree, &keys, &metric)
+            .unwrap()
+            .to_bytes(&keys, &metric);
+
+        let mut changed = keys.clone();
+        changed.push("new");
+        assert_eq!(
+            Index::from_bytes(read(&bytes), &changed, &metric).unwrap_err(),
+            "index was saved for a different corpus"
+        );
+        assert_eq!(
+            Index::from_bytes(read(&bytes), &keys, &DamerauLevenshtein::new()).unwrap_err(),
+            "index was saved for a different metric"
+        );
+    }
+
+    #[test]
+    fn corrupted_indexes_are_rejected() {
+        let keys = corpus();
+        let metric = Levenshtein::new();
+
+        for &kind in KINDS {
+            let bytes = Index::build(kind, &keys, &metric)
+                .unwrap()
+                .to_bytes(&keys, &metric);
+
+            for position in [0, 8, 12, 13, 15, HEADER_LEN, bytes.len() - 1] {
+                let mut corrupted = bytes.clone();
+                corrupted[position] ^= 0x40;
+                assert!(Index::from_bytes(read(&corrupted), &keys, &metric).is_err());
+            }
+            for len in [0, 7, HEADER_LEN - 1, bytes.len() - 1] {
+                assert!(Index::from_bytes(read(&bytes[..len]), &keys, &metric).is_err());
+            }
+        }
+    }
+
+    /// Overwrites the word at `offset` of an index file, and updates the
+    /// checksum to match.
+    fn forge(bytes: &[u8], offset: usize, word: u32) -> Vec<u8> {
+        let mut forged = bytes.to_vec();
+        forged[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
+        let checksum = checksum(&forged[HEADER_LEN..]);
+        forged[40..48].copy_from_slice(&checksum.to_le_bytes());
+        forged
+    }
+
+    #[test]
+    fn payloads_are_validated_even_with_valid_checksums() {
+        let keys = corpus();
+        let metric = Levenshtein::new();
+        let bytes = Index::build(IndexKind::BkTree, &keys, &metric)
+            .unwrap()
+            .to_bytes(&keys, &metric);
+
+        // Point the root at a string beyond the corpus, and fix up the
+        // checksum so that only the payload's validation can catch it.
+        assert_eq!(
+            Index::from_bytes(
+                read(&forge(&bytes, HEADER_LEN + 8, u32::MAX)),
+                &keys,
+                &metric
+            )
+            .unwrap_err(),
+            "index file refers to a string beyond the corpus"
+        );
+
+        // Point the trie's first edge back at the root, which would make a
+        // cycle.
+        let bytes = Index::build(IndexKind::LevenshteinAutomaton, &keys, &metric)
+            .unwrap()
+            .to_bytes(&keys, &metric);
+        let nodes = HEADER_LEN + 8 + 8 + 4 * keys.len();
+        let node_count = u64::from_le_bytes(bytes[nodes..nodes + 8].try_into().unwrap());
+        let first_child = nodes + 8 + 16 * node_count as usize + 8 + 4;
+        assert_eq!(
+            Index::from_bytes(read(&forge(&bytes, first_child, 0)), &keys, &metric).unwrap_err(),
+            "index file holds a malformed trie"
+        );
+    }
+
+    #[test]
+    fn reserved_header_bytes_must_be_zero() {
+        let keys = corpus();
+        let metric = Levenshtein::new();
+        let mut bytes = Index::build(IndexKind::LevenshteinAutomaton, &keys, &metric)
+            .unwrap()
+            .to_bytes(&keys, &metric);
+
+        bytes[14] = 1;
+        assert_eq!(
+            Index::from_bytes(read(&bytes), &keys, &metric).unwrap_err(),
+            "index file has reserved header bytes set"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! A symmetric-delete index in the style of SymSpell.
+//!
+//! Two strings are within `d` edits of each other only if deleting at most
+//! `d` characters from each can make them equal. The index precomputes every
+//! string reachable by deleting up to `max_distance` characters from each
+//! corpus key, so a query only needs to generate its own deletes and look
+//! them up. Only the first `prefix_length` characters of each string are
+//! used, which bounds the number of deletes per key while still finding
+//! every key within `max_distance` edits.
+//!
+//! Deletes are stored as 32-bit hashes in a single sorted table. Hash
+//! collisions can only add candidates, which are always verified with the
+//! searcher's metric.
+
+use std::collections::BinaryHeap;
+
+use super::{Decoder, Encoder, Postings, hash_chars};
+use crate::{Candidate, Corpus, Metric};
+
+#[derive(Debug)]
+pub(crate) struct SymSpell {
+    max_distance: usize,
+    prefix_length: usize,
+    /// The keys each delete was generated from.
+    deletes: Postings,
+}
+
+impl SymSpell {
+    /// Builds an index of the deletes of every key.
+    pub(crate) fn build(keys: &Corpus, max_distance: usize, prefix_length: usize) -> Self {
+        let mut deletes = Vec::new();
+        let mut key_deletes = Vec::new();
+
+        for (index, key) in keys.iter().enumerate() {
+            key_deletes.clear();
+            collect_deletes(key, max_distance, prefix_length, &mut key_deletes);
+
+            let index = u32::try_from(index).expect("corpus is too large for a SymSpell index");
+            deletes.extend(key_deletes.iter().map(|&hash| (hash, index)));
+        }
+
+        Self {
+            max_distance,
+            prefix_length,
+            deletes: Postings::new(deletes),
+        }
+    }
+
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        self.deletes.encode(out);
+    }
+
+    pub(crate) fn decode(
+        input: &mut Decoder<'_>,
+        keys: &Corpus,
+        max_distance: usize,
+        prefix_length: usize,
+    ) -> Result<Self, &'static str> {
+        Ok(Self {
+            max_distance,
+            prefix_length,
+            deletes: Postings::decode(input, keys)?,
+        })
+    }
+
+    /// The largest distance this index can find every key within.
+    pub(crate) fn max_distance(&self) -> usize {
+        self.max_distance
+    }
+
+    /// The number of leading characters of each key that is indexed.
+    pub(crate) fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    /// Finds every key within `max_distance` of `arg`, sorted from best to
+    /// worst match.
+    ///
+    /// `max_distance` must not exceed [`SymSpell::max_distance`].
+    pub(crate) fn within_distance(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        max_distance: f64,
+    ) -> Vec<Candidate> {
+        let mut matches: Vec<Candidate> = self
+            .candidates(arg)
+            .into_iter()
+            .filter_map(|index| Candidate::within(arg, &keys[index], index, metric, max_distance))
+            .collect();
+
+        matches.sort_unstable();
+        matches
+    }
+
+    /// Finds the `k` keys closest to `arg`, sorted from best to worst match,
+    /// if at least `k` keys are within [`SymSpell::max_distance`] of it.
+    ///
+    /// Returns `None` otherwise, as the remaining matches could be anywhere
+    /// in the corpus.
+    pub(crate) fn top_k(
+        &self,
+        arg: &str,
+        keys: &Corpus,
+        metric: &dyn Metric,
+        k: usize,
+    ) -> Option<Vec<Candidate>> {
+        let matches = self.within_distance(arg, keys, metric, self.max_distance as f64);
+        if matches.len() < k {
+            return None;
+        }
+
+        let mut best: BinaryHeap<Candidate> = matches.into_iter().collect();
+        while best.len() > k {
+            best.pop();
+        }
+
+        Some(best.into_sorted_vec())
+    }
+
+    /// Returns the corpus indexes of every key that shares a delete with
+    /// `arg`, without duplicates.
+    fn candidates(&self, arg: &str) -> Vec<usize> {
+        let mut arg_deletes = Vec::new();
+        collect_deletes(arg, self.max_distance, self.prefix_length, &mut arg_deletes);
+
+        let mut candidates = Vec::new();
+        for hash in arg_deletes {
+            candidates.extend(self.deletes.get(hash));
+        }
+
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
+/// Pushes the hash of every string reachable by deleting up to
+/// `max_distance` characters from the first `prefix_length` characters of
+/// `text`, without duplicates.
+fn collect_deletes(text: &str, max_distance: usize, prefix_length: usize, out: &mut Vec<u32>) {
+    let prefix: Vec<char> = text.chars().take(prefix_length).collect();
+
+    // Generate deletes one level at a time, each level having one more
+    // character deleted than the last.
+    let mut level = vec![prefix];
+    for _ in 0..max_distance {
+        let mut next = Vec::new();
+        for chars in &level {
+            for skip in 0..chars.len() {
+                let mut delete = chars.clone();
+                delete.remove(skip);
+                next.push(delete);
+            }
+        }
+        next.sort_unstable();
+        next.dedup();
+
+        out.extend(level.iter().map(|chars| hash_chars(chars)));
+        level = next;
+    }
+    out.extend(level.iter().map(|chars| hash_chars(chars)));
+
+    out.sort_unstable();
+    out.dedup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::tests::corpus;
+    use crate::{Levenshtein, find_top_k};
+
+    #[test]
+    fn top_k_needs_k_keys_within_the_maximum_distance() {
+        let keys = corpus();
+        let metric = Levenshtein::new();
+        let index = SymSpell::build(&keys, 1, 7);
+
+        // Only "hello" itself, its duplicate, "hell" and "helo" are within
+        // one edit of "hello", and the fifth closest key could be anywhere.
+        assert_eq!(
+            index.top_k("hello", &keys, &metric, 4),
+            Some(find_top_k("hello", &keys, &metric, 4))
+        );
+        assert_eq!(index.top_k("hello", &keys, &metric, 5), None);
+    }
+}
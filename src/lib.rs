@@ -1,117 +1,1189 @@
 use std::{
-    io::Read,
-    path::{self, Path},
+    borrow::Cow,
+    cmp::Ordering,
+    collections::BinaryHeap,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    str::{FromStr, Utf8Error},
 };
 
+use memmap2::Mmap;
 use thiserror::Error;
 
+pub mod corpus;
+mod index;
+pub mod metric;
+pub mod normalize;
+pub mod rank;
+
+use index::{Index, IndexBytes};
+
+pub use corpus::{Corpus, CorpusFormat};
+pub use index::IndexKind;
+
+pub use metric::{
+    DamerauLevenshtein, Hamming, Jaro, JaroWinkler, Levenshtein, Metric, OptimalStringAlignment,
+    Positional, Unit, damerau_levenshtein, hamming, jaro, jaro_winkler, levenshtein, osa_distance,
+};
+pub use normalize::{Normalizer, UnicodeForm, match_case};
+pub use rank::{Blend, ByDistance, LogWeight};
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {}
+
+    fn to_strings(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn to_corpus(strs: &[&str]) -> Corpus {
+        strs.iter().collect()
+    }
+
+    fn closest<'a>(arg: &str, corpus: &'a Corpus, metric: &dyn Metric) -> &'a str {
+        &corpus[find_closest_str(arg, corpus, metric).unwrap().index]
+    }
+
+    fn texts<'a>(corpus: &'a Corpus, candidates: &[Candidate]) -> Vec<&'a str> {
+        candidates
+            .iter()
+            .map(|candidate| &corpus[candidate.index])
+            .collect()
+    }
+
+    #[test]
+    fn closest_str_prefers_fewest_edits() {
+        let corpus = to_corpus(&["yellow", "hello", "help"]);
+        assert_eq!(closest("helo", &corpus, &Levenshtein::new()), "hello");
+    }
+
+    #[test]
+    fn closest_str_borrows_from_the_corpus() {
+        let searcher = FuzzySearcher::from_strings(to_strings(&["yellow", "hello"]));
+        let closest = searcher.search("helo").unwrap();
+
+        assert!(std::ptr::eq(closest.text, &searcher.corpus[1]));
+        assert_eq!(closest.index, 1);
+        assert_eq!(closest.distance, 1.0);
+        assert_eq!(closest.score, 0.8);
+        assert!(find_closest_str("helo", &Corpus::new(), &Levenshtein::new()).is_none());
+    }
+
+    #[test]
+    fn closest_str_uses_the_given_metric() {
+        let corpus = to_corpus(&["xhelo", "hexx"]);
+        assert_eq!(closest("helo", &corpus, &Levenshtein::new()), "xhelo");
+        assert_eq!(closest("helo", &corpus, &Positional::new()), "hexx");
+    }
+
+    #[test]
+    fn closest_str_treats_transpositions_as_one_edit() {
+        let corpus = to_corpus(&["tax", "the"]);
+        assert_eq!(closest("teh", &corpus, &Levenshtein::new()), "tax");
+        assert_eq!(
+            closest("teh", &corpus, &OptimalStringAlignment::new()),
+            "the"
+        );
+        assert_eq!(closest("teh", &corpus, &DamerauLevenshtein::new()), "the");
+    }
+
+    #[test]
+    fn top_k_returns_ranked_results() {
+        let corpus = to_corpus(&["yellow", "help", "hello", "world", "helo"]);
+        let results = find_top_k("helo", &corpus, &Levenshtein::new(), 3);
+
+        assert_eq!(texts(&corpus, &results), ["helo", "hello", "help"]);
+        assert_eq!(results[1].index, 2);
+        assert_eq!(results[0].score, 1.0);
+        assert_eq!(results[1].score, 0.8);
+        assert_eq!(results[2].score, 0.75);
+    }
+
+    #[test]
+    fn within_filters_by_distance_and_score() {
+        let corpus = to_corpus(&["yellow", "help", "hello", "world", "helo"]);
+
+        let within_one = find_within(
+            "helo",
+            &corpus,
+            &Levenshtein::new(),
+            Threshold::MaxDistance(1.0),
+        );
+        assert_eq!(texts(&corpus, &within_one), ["helo", "hello", "help"]);
+
+        let above = find_within(
+            "helo",
+            &corpus,
+            &Levenshtein::new(),
+            Threshold::MinScore(0.8),
+        );
+        assert_eq!(texts(&corpus, &above), ["helo", "hello"]);
+    }
+
+    #[test]
+    fn within_returns_nothing_when_nothing_is_close() {
+        let corpus = to_corpus(&["yellow", "world"]);
+        assert!(
+            find_within(
+                "helo",
+                &corpus,
+                &Levenshtein::new(),
+                Threshold::MaxDistance(1.0)
+            )
+            .is_empty()
+        );
+        assert!(
+            find_within(
+                "helo",
+                &corpus,
+                &Levenshtein::new(),
+                Threshold::MinScore(0.9)
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn new_loads_the_given_path() {
+        let path = std::env::temp_dir().join("fuzzy_search_new_loads_the_given_path.txt");
+        std::fs::write(&path, "apple\nbanana\ncherry").unwrap();
+
+        let searcher = FuzzySearcher::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(searcher.search("banan").unwrap().text, "banana");
+        assert!(FuzzySearcher::new(path).is_err());
+    }
+
+    #[test]
+    fn mapped_corpora_match_loaded_corpora() {
+        let path = std::env::temp_dir().join("fuzzy_search_mapped_corpora_match.txt");
+        std::fs::write(&path, "apple\nbanana\ncaf\u{e9}\n").unwrap();
+
+        let loaded = FuzzySearcher::new(&path).unwrap();
+        // SAFETY: the file is not modified until the searcher is dropped.
+        let mapped = unsafe { FuzzySearcher::new_mapped(&path) }.unwrap();
+
+        assert!(mapped.corpus().is_mapped());
+        assert_eq!(mapped.corpus(), loaded.corpus());
+        assert_eq!(mapped.search("cafe").unwrap().text, "caf\u{e9}");
+        drop(mapped);
+
+        std::fs::write(&path, b"apple\nch\xFFerry").unwrap();
+        // SAFETY: the file is not modified while it is mapped.
+        let result = unsafe { FuzzySearcher::new_mapped(&path) };
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(FuzzySearchError::InvalidCorpusEncoding { line: 2, .. })
+        ));
+        assert!(matches!(
+            // SAFETY: the file does not exist, so nothing is mapped.
+            unsafe { FuzzySearcher::new_mapped(&path) },
+            Err(FuzzySearchError::UnableToOpenCorpusFile { .. })
+        ));
+    }
+
+    #[test]
+    fn corpus_formats_apply_to_every_loader() {
+        let path = std::env::temp_dir().join("fuzzy_search_corpus_formats.txt");
+        let text = "\u{feff}# fruit\r\napple\r\n\r\nbanana\r\napple\r\n";
+        std::fs::write(&path, text).unwrap();
+
+        let format = CorpusFormat::new().with_comments(true).with_dedup(true);
+        let loaded = format.load(&path).unwrap();
+        // SAFETY: the file is not modified until the corpus is dropped.
+        let mapped = unsafe { format.map(&path) }.unwrap();
+        let read = format.read(text.as_bytes()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected: Corpus = ["apple", "banana"].into_iter().collect();
+        assert_eq!(loaded, expected);
+        assert_eq!(mapped, expected);
+        assert_eq!(read, expected);
+
+        let searcher = FuzzySearcher::from_corpus(mapped);
+        let closest = searcher.search("banan").unwrap();
+        assert_eq!((closest.text, closest.index), ("banana", 1));
+
+        let default: FuzzySearcher = text.parse().unwrap();
+        assert_eq!(default.corpus().len(), 4);
+    }
+
+    #[test]
+    fn weighted_rankings_prefer_frequent_strings() {
+        let text = "# word\tcount\nthy\t2\nthe\t1000000\nthaw\t5\nthen\t40\n";
+        let corpus = CorpusFormat::new()
+            .with_comments(true)
+            .with_weights(true)
+            .read(text.as_bytes())
+            .unwrap();
+        let searcher = FuzzySearcher::from_corpus(corpus);
+        assert_eq!(searcher.search("thw").unwrap().text, "thaw");
+
+        let searcher = searcher.with_ranking(ByDistance::new());
+        let closest = searcher.search("thw").unwrap();
+        assert_eq!((closest.text, closest.weight), ("the", 1e6));
+        assert_eq!(searcher.search("thenn").unwrap().text, "then");
+
+        let searcher = searcher
+            .with_ranking(LogWeight::new())
+            .with_index(IndexKind::BkTree)
+            .unwrap();
+        assert_eq!(searcher.search("thenn").unwrap().text, "the");
+
+        fn texts(matches: Vec<Match<'_>>) -> Vec<&str> {
+            matches.iter().map(|m| m.text).collect()
+        }
+        assert_eq!(
+            texts(searcher.search_top_k("thw", 4).unwrap()),
+            ["the", "thaw", "thy", "then"]
+        );
+        assert_eq!(
+            texts(
+                searcher
+                    .search_within("thw", Threshold::MaxDistance(1.0))
+                    .unwrap()
+            ),
+            ["the", "thaw", "thy"]
+        );
+
+        let inverse = searcher.with_ranking(|distance: f64, weight: f64| distance + weight);
+        assert_eq!(inverse.search("thw").unwrap().text, "thy");
+    }
+
+    #[test]
+    fn blended_top_k_matches_ranking_every_string() {
+        let mut corpus = Corpus::new();
+        for (i, word) in [
+            "hello", "help", "yellow", "hell", "shell", "world", "word", "held",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            corpus.push_weighted(word, (i * 37 % 11) as f64);
+        }
+
+        let blends: [fn(FuzzySearcher) -> FuzzySearcher; 3] = [
+            |searcher| searcher.with_ranking(ByDistance::new()),
+            |searcher| searcher.with_ranking(LogWeight::new().with_scale(2.0)),
+            |searcher| searcher.with_ranking(|distance: f64, weight: f64| distance * weight),
+        ];
+        for blend in blends {
+            for index in [None, Some(IndexKind::LevenshteinAutomaton)] {
+                let mut searcher = blend(FuzzySearcher::from_corpus(corpus.clone()));
+                if let Some(kind) = index {
+                    searcher = searcher.with_index(kind).unwrap();
+                }
+
+                for query in ["helo", "wrd", "xyzzy"] {
+                    let everything = searcher
+                        .search_within(query, Threshold::MaxDistance(f64::INFINITY))
+                        .unwrap();
+                    for k in [1, 3, 20] {
+                        let expected = &everything[..k.min(everything.len())];
+                        assert_eq!(searcher.search_top_k(query, k).unwrap(), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_weights_carry_the_line() {
+        let Err(err) = CorpusFormat::new()
+            .with_weights(true)
+            .read("the\t10\n\nthy two\n".as_bytes())
+        else {
+            panic!("expected a missing weight to fail");
+        };
+
+        assert_eq!(err.to_string(), "Corpus has an invalid weight on line 3");
+    }
+
+    #[test]
+    fn open_errors_carry_the_path_and_source() {
+        let path = std::env::temp_dir().join("fuzzy_search_missing_corpus.txt");
+        let Err(err) = FuzzySearcher::new(&path) else {
+            panic!("expected missing corpus to fail");
+        };
+
+        assert!(err.to_string().contains("fuzzy_search_missing_corpus.txt"));
+        match err {
+            FuzzySearchError::UnableToOpenCorpusFile {
+                path: err_path,
+                source,
+            } => {
+                assert_eq!(err_path, path);
+                assert_eq!(source.kind(), io::ErrorKind::NotFound);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decoding_errors_carry_the_line() {
+        let bytes: &[u8] = b"apple\nbanana\nch\xFFerry\n";
+        let Err(err) = FuzzySearcher::from_reader(bytes) else {
+            panic!("expected invalid UTF-8 to fail");
+        };
+
+        assert_eq!(err.to_string(), "Corpus contains invalid UTF-8 on line 3");
+        assert!(matches!(
+            err,
+            FuzzySearchError::InvalidCorpusEncoding {
+                path: None,
+                line: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn alternative_constructors_load_the_same_corpus() {
+        let text = "apple\nbanana\ncherry";
+        let searchers = [
+            FuzzySearcher::from_strings(vec!["apple".into(), "banana".into(), "cherry".into()]),
+            ["apple", "banana", "cherry"].into_iter().collect(),
+            FuzzySearcher::from_reader(text.as_bytes()).unwrap(),
+            text.parse().unwrap(),
+        ];
+
+        for searcher in searchers {
+            let closest = searcher.search("chery").unwrap();
+            assert_eq!((closest.text, closest.index), ("cherry", 2));
+        }
+    }
+
+    #[test]
+    fn empty_queries_are_rejected() {
+        let searcher: FuzzySearcher = ["apple", "banana"].into_iter().collect();
+
+        assert!(matches!(
+            searcher.search(""),
+            Err(FuzzySearchError::InvalidQuery { .. })
+        ));
+        assert!(matches!(
+            searcher.search_top_k("", 2),
+            Err(FuzzySearchError::InvalidQuery { .. })
+        ));
+        assert!(matches!(
+            searcher.search_within("", Threshold::MinScore(0.5)),
+            Err(FuzzySearchError::InvalidQuery { .. })
+        ));
+    }
+
+    #[test]
+    fn empty_corpora_are_rejected() {
+        let searcher = FuzzySearcher::from_strings(Vec::new());
+
+        assert!(matches!(
+            searcher.search("apple"),
+            Err(FuzzySearchError::EmptyCorpus)
+        ));
+        assert!(matches!(
+            searcher.search_top_k("apple", 2),
+            Err(FuzzySearchError::EmptyCorpus)
+        ));
+        assert!(matches!(
+            searcher.search_within("apple", Threshold::MinScore(0.5)),
+            Err(FuzzySearchError::EmptyCorpus)
+        ));
+    }
+
+    #[test]
+    fn empty_strings_score_without_nan() {
+        for metric in [
+            &Levenshtein::new() as &dyn Metric,
+            &DamerauLevenshtein::new(),
+            &Hamming::new(),
+            &Positional::new(),
+            &JaroWinkler::new(),
+        ] {
+            let score = metric.similarity("apple", "");
+            assert!((0.0..=1.0).contains(&score));
+            assert_eq!(metric.similarity("", ""), 1.0);
+        }
+    }
+
+    #[test]
+    fn normalized_searches_return_original_text() {
+        let searcher = FuzzySearcher::from_strings(to_strings(&["Caf\u{e9}", "Cabbage"]))
+            .with_normalizer(
+                Normalizer::new()
+                    .with_case_fold(true)
+                    .with_strip_diacritics(true),
+            );
+
+        let closest = searcher.search("CAFE").unwrap();
+        assert_eq!(closest.text, "Caf\u{e9}");
+        assert_eq!(closest.distance, 0.0);
+
+        let within = searcher
+            .search_within("cafe\u{301}", Threshold::MaxDistance(0.0))
+            .unwrap();
+        assert_eq!(within.len(), 1);
+        assert_eq!(within[0].text, "Caf\u{e9}");
+    }
+
+    #[test]
+    fn case_insensitive_corrections_preserve_query_casing() {
+        let searcher = FuzzySearcher::from_strings(to_strings(&["Hello", "yellow"]))
+            .with_case_insensitive(true)
+            .with_preserve_case(true);
+
+        assert_eq!(searcher.search("hELLO").unwrap().distance, 0.0);
+        assert_eq!(searcher.correct("Helo").unwrap(), "Hello");
+        assert_eq!(searcher.correct("HELO").unwrap(), "HELLO");
+        assert_eq!(searcher.correct("helo").unwrap(), "hello");
+
+        let searcher = searcher.with_preserve_case(false);
+        assert_eq!(searcher.correct("HELO").unwrap(), "Hello");
+    }
+
+    #[test]
+    fn queries_empty_after_normalization_are_rejected() {
+        let searcher = FuzzySearcher::from_strings(to_strings(&["apple"])).with_normalizer(
+            Normalizer::new()
+                .with_remove_punctuation(true)
+                .with_collapse_whitespace(true),
+        );
+
+        assert!(matches!(
+            searcher.search(" ?! "),
+            Err(FuzzySearchError::InvalidQuery { .. })
+        ));
+    }
+
+    #[test]
+    fn indexed_searches_match_linear_scans() {
+        let corpus = to_strings(&["hello", "help", "yellow", "hell", "world", "Hello"]);
+        let linear = FuzzySearcher::from_strings(corpus.clone()).with_case_insensitive(true);
+        for kind in [
+            IndexKind::BkTree,
+            IndexKind::SymSpell {
+                max_distance: 1,
+                prefix_length: 7,
+            },
+            IndexKind::LevenshteinAutomaton,
+            IndexKind::NGram {
+                gram_size: 2,
+                min_overlap: 0.5,
+            },
+        ] {
+            let indexed = FuzzySearcher::from_strings(corpus.clone())
+                .with_case_insensitive(true)
+                .with_index(kind)
+                .unwrap();
+
+            for query in ["helo", "HELL", "wrld", "xyzzy"] {
+                assert_eq!(
+                    indexed.search(query).unwrap(),
+                    linear.search(query).unwrap()
+                );
+                assert_eq!(
+                    indexed.search_top_k(query, 3).unwrap(),
+                    linear.search_top_k(query, 3).unwrap()
+                );
+                for threshold in [
+                    Threshold::MaxDistance(1.0),
+                    Threshold::MaxDistance(3.0),
+                    Threshold::MinScore(0.5),
+                ] {
+                    assert_eq!(
+                        indexed.search_within(query, threshold).unwrap(),
+                        linear.search_within(query, threshold).unwrap()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn saved_indexes_can_be_loaded_by_matching_searchers() {
+        let path = std::env::temp_dir().join("fuzzy_search_saved_indexes.idx");
+        let corpus = ["hello", "help", "yellow", "Hello", "world"];
+        let searcher: FuzzySearcher = corpus.into_iter().collect();
+
+        assert!(matches!(
+            searcher.save_index(&path),
+            Err(FuzzySearchError::NoIndex)
+        ));
+
+        let indexed = searcher.with_index(IndexKind::BkTree).unwrap();
+        indexed.save_index(&path).unwrap();
+
+        let loaded: FuzzySearcher = corpus.into_iter().collect();
+        let loaded = loaded.load_index(&path).unwrap();
+        assert_eq!(
+            loaded.index.as_ref().map(Index::kind),
+            Some(IndexKind::BkTree)
+        );
+        assert_eq!(
+            loaded.search_top_k("helo", 3).unwrap(),
+            indexed.search_top_k("helo", 3).unwrap()
+        );
+
+        // SAFETY: the file is not modified until the searcher is dropped.
+        let mapped = unsafe { FuzzySearcher::from_iter(corpus).load_index_mapped(&path) }.unwrap();
+        assert_eq!(
+            mapped.search_top_k("helo", 3).unwrap(),
+            indexed.search_top_k("helo", 3).unwrap()
+        );
+        drop(mapped);
+
+        // Case folding changes the keys the index was built over.
+        let folded: FuzzySearcher = corpus.into_iter().collect();
+        let result = folded.with_case_insensitive(true).load_index(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let Err(err) = result else {
+            panic!("expected an index for different keys to be rejected");
+        };
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Invalid index file `{}`: index was saved for a different corpus",
+                path.display()
+            )
+        );
+        assert!(matches!(
+            FuzzySearcher::from_iter(corpus).load_index(&path),
+            Err(FuzzySearchError::UnableToAccessIndexFile { .. })
+        ));
+    }
+
+    #[test]
+    fn indexes_require_a_compatible_metric() {
+        let searcher = FuzzySearcher::from_strings(to_strings(&["hello"]))
+            .with_metric(OptimalStringAlignment::new());
+
+        assert!(matches!(
+            searcher.with_index(IndexKind::BkTree),
+            Err(FuzzySearchError::IncompatibleIndex { .. })
+        ));
+
+        let searcher =
+            FuzzySearcher::from_strings(to_strings(&["hello"])).with_metric(JaroWinkler::new());
+        assert!(matches!(
+            searcher.with_index(IndexKind::SymSpell {
+                max_distance: 2,
+                prefix_length: 7
+            }),
+            Err(FuzzySearchError::IncompatibleIndex { .. })
+        ));
+
+        let searcher =
+            FuzzySearcher::from_strings(to_strings(&["hello"])).with_metric(JaroWinkler::new());
+        assert!(matches!(
+            searcher.with_index(IndexKind::NGram {
+                gram_size: 3,
+                min_overlap: 0.5
+            }),
+            Err(FuzzySearchError::IncompatibleIndex { .. })
+        ));
+    }
+
+    #[test]
+    fn top_k_handles_small_k_and_corpora() {
+        let corpus = to_corpus(&["a", "b"]);
+        assert!(find_top_k("a", &corpus, &Levenshtein::new(), 0).is_empty());
+        assert_eq!(find_top_k("a", &corpus, &Levenshtein::new(), 5).len(), 2);
+    }
+
+    #[test]
+    fn scans_spanning_chunks_break_ties_by_index() {
+        // Many equally close strings, spread across several chunks.
+        let words = ["cat", "bat", "hat", "cart", "dog"];
+        let corpus: Corpus = (0..3 * SCAN_CHUNK_SIZE + 7)
+            .map(|i| words[(i * 7) % words.len()])
+            .collect();
+        let metric = Levenshtein::new();
+
+        let mut all: Vec<Candidate> = corpus
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| Candidate::new("rat", s, idx, &metric))
+            .collect();
+        all.sort();
+
+        assert_eq!(find_closest_str("rat", &corpus, &metric), Some(all[0]));
+        for k in [1, 10, SCAN_CHUNK_SIZE + 3] {
+            let top_k = find_top_k("rat", &corpus, &metric, k);
+            assert_eq!(top_k, all[..k]);
+            assert!(top_k.windows(2).all(|pair| pair[0].index < pair[1].index));
+        }
+
+        let within = find_within("rat", &corpus, &metric, Threshold::MaxDistance(1.0));
+        assert_eq!(within, all[..within.len()]);
+        assert!(all[within.len()].distance > 1.0);
+    }
 }
-/// Errors that can occur when loading a corpus.
+/// Errors that can occur when loading or searching a corpus.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum FuzzySearchError {
     /// Unable to open the corpus file.
-    #[error("Unable to open corpus file")]
-    UnableToOpenCorpusFile,
+    #[error("Unable to open corpus file `{}`", path.display())]
+    UnableToOpenCorpusFile {
+        /// The path of the corpus file.
+        path: PathBuf,
+        /// The underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
 
     /// Unable to read the corpus file to a string.
-    #[error("Unable to read corpus file to string")]
-    UnableToReadCorpusFileToString,
+    #[error("Unable to read corpus{} to string", describe_path(path))]
+    UnableToReadCorpusFileToString {
+        /// The path of the corpus file, or `None` if the corpus was read from
+        /// a reader.
+        path: Option<PathBuf>,
+        /// The underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// The corpus is not valid UTF-8.
+    #[error("Corpus{} contains invalid UTF-8 on line {line}", describe_path(path))]
+    InvalidCorpusEncoding {
+        /// The path of the corpus file, or `None` if the corpus was read from
+        /// a reader.
+        path: Option<PathBuf>,
+        /// The line containing the first invalid byte, counting from 1.
+        line: usize,
+        /// The underlying decoding error.
+        #[source]
+        source: Utf8Error,
+    },
+
+    /// A line of a weighted corpus does not end with a valid weight.
+    #[error("Corpus{} has an invalid weight on line {line}", describe_path(path))]
+    InvalidCorpusWeight {
+        /// The path of the corpus file, or `None` if the corpus was read from
+        /// a reader or parsed from text.
+        path: Option<PathBuf>,
+        /// The line with the invalid weight, counting from 1.
+        line: usize,
+    },
+
+    /// The corpus text is too large to be stored in a [`Corpus`], which holds
+    /// at most 4 GiB.
+    #[error("Corpus{} exceeds 4 GiB", describe_path(path))]
+    CorpusTooLarge {
+        /// The path of the corpus file, or `None` if the corpus was read from
+        /// a reader or parsed from text.
+        path: Option<PathBuf>,
+    },
+
+    /// The corpus has no entries to search.
+    #[error("Corpus is empty")]
+    EmptyCorpus,
+
+    /// The requested index cannot be built for the searcher's metric.
+    #[error("Incompatible index: {reason}")]
+    IncompatibleIndex {
+        /// Why the index cannot be built.
+        reason: &'static str,
+    },
+
+    /// The searcher has no index to save.
+    #[error("Searcher has no index")]
+    NoIndex,
+
+    /// Unable to read or write an index file.
+    #[error("Unable to access index file `{}`", path.display())]
+    UnableToAccessIndexFile {
+        /// The path of the index file.
+        path: PathBuf,
+        /// The underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// The index file is corrupted, in an unsupported format, or was saved
+    /// for a different corpus or metric.
+    #[error("Invalid index file `{}`: {reason}", path.display())]
+    InvalidIndexFile {
+        /// The path of the index file.
+        path: PathBuf,
+        /// Why the index file was rejected.
+        reason: &'static str,
+    },
+
+    /// The query cannot be searched for.
+    #[error("Invalid query: {reason}")]
+    InvalidQuery {
+        /// Why the query was rejected.
+        reason: &'static str,
+    },
+}
+
+/// Formats an optional corpus path for use in an error message.
+fn describe_path(path: &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => format!(" file `{}`", path.display()),
+        None => String::new(),
+    }
 }
 
 /// Loads a corpus from a file.
 ///
-/// The file is expected to be newline-separated lines of text.
-/// Each line is loaded as a separate string.
+/// The file is expected to be newline-separated lines of text, which are
+/// split into entries as described by `format`.
 ///
 /// # Errors
 ///
 /// Returns an error if the corpus file is unable to be opened or read.
-fn load_corpus<P: AsRef<Path>>(path: P) -> Result<Vec<String>, FuzzySearchError> {
+fn load_corpus<P: AsRef<Path>>(path: P, format: CorpusFormat) -> Result<Corpus, FuzzySearchError> {
+    let path = path.as_ref();
+
+    // Open corpus file
+    let corpus_file =
+        std::fs::File::open(path).map_err(|source| FuzzySearchError::UnableToOpenCorpusFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    read_corpus(corpus_file, Some(path), format)
+}
+
+/// Memory-maps a corpus file, so that its pages are shared with any other
+/// process mapping the same file.
+///
+/// Files that cannot be mapped are read into memory instead.
+///
+/// # Safety
+///
+/// The file must not be modified while the corpus exists.
+///
+/// # Errors
+///
+/// Returns an error if the corpus file is unable to be opened or read, or is
+/// not valid UTF-8.
+unsafe fn map_corpus<P: AsRef<Path>>(
+    path: P,
+    format: CorpusFormat,
+) -> Result<Corpus, FuzzySearchError> {
+    let path = path.as_ref();
+
     // Open corpus file
-    let mut corpus_file =
-        std::fs::File::open(path).map_err(|_| FuzzySearchError::UnableToOpenCorpusFile)?;
+    let corpus_file =
+        std::fs::File::open(path).map_err(|source| FuzzySearchError::UnableToOpenCorpusFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
 
-    // Read corpus file to string
-    let mut corpus = String::new();
-    corpus_file
-        .read_to_string(&mut corpus)
-        .map_err(|_| FuzzySearchError::UnableToReadCorpusFileToString)?;
+    // SAFETY: the caller guarantees that the file is not modified while the
+    // mapping exists.
+    let Ok(mmap) = (unsafe { Mmap::map(&corpus_file) }) else {
+        return read_corpus(corpus_file, Some(path), format);
+    };
 
-    // Split corpus into lines, creating a vector of strings
-    Ok(corpus.split("\n").map(|s| s.to_string()).collect())
+    // Check the mapped bytes, reporting the line of the first invalid byte on failure
+    let text =
+        std::str::from_utf8(&mmap).map_err(|source| invalid_encoding(&mmap, source, Some(path)))?;
+    let corpus::Entries { spans, weights } = format.split(text, Some(path))?;
+
+    // SAFETY: the text was just checked to be valid UTF-8, and the caller
+    // guarantees that the file is not modified while the corpus exists.
+    Ok(unsafe { Corpus::from_mapped(mmap, spans) }.with_weights(weights))
 }
 
-/// Finds the string in the corpus closest to the given string.
+/// Loads a corpus from a reader.
 ///
-/// The distance is calculated as a normalized value between 0 and 1.
-/// The correctness of a string is 1 - distance, so that correctness ranges
-/// from 0 (completely incorrect) to 1 (completely correct).
-fn find_closest_str<'a>(arg: &'a str, reference_strs: &'a [String]) -> String {
-    let mut closest_str = &reference_strs[0];
-    let mut closest_distance = arg.len() as f64;
-
-    // Define a closure to calculate the distance between two strings
-    // The distance between two strings is the number of characters that must be
-    // changed to transform one string into another.
-    // The distance is calculated as a normalized value between 0 and 1.
-    let distance = |a: &str, b: &str| -> f64 {
-        let mut distance = 0;
-
-        // Calculate the absolute difference in length between a and b
-        // Add this to the distance, because the distance is calculated as a
-        // normalized value between 0 and 1.
-        // Case 0: a is longer than b, so add nonzero distance using abs_diff.
-        // Case 1: b is longer than a, so add nonzero distance using abs_diff.
-        // Case 2: a and b are the same length, so add zero distance
-        let a_len = a.len();
-        let b_len = b.len();
-        distance += a_len.abs_diff(b_len);
-
-        for (a_char, b_char) in a.chars().zip(b.chars()) {
-            // If the characters are not equal, add one to the distance.
-            if a_char != b_char {
-                distance += 1;
-            }
+/// The reader is expected to produce newline-separated lines of text.
+///
+/// # Errors
+///
+/// Returns an error if the reader fails, does not produce valid UTF-8, or
+/// produces more than 4 GiB.
+fn read_corpus<R: Read>(
+    mut reader: R,
+    path: Option<&Path>,
+    format: CorpusFormat,
+) -> Result<Corpus, FuzzySearchError> {
+    // Read corpus to bytes
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(|source| {
+        FuzzySearchError::UnableToReadCorpusFileToString {
+            path: path.map(Path::to_path_buf),
+            source,
         }
+    })?;
 
-        // Normalize the distance by dividing by the length of the argument string.
-        let normalized_distance = distance as f64 / a_len as f64;
-        normalized_distance
-    };
+    // Decode the bytes in place, reporting the line of the first invalid byte on failure
+    let text = String::from_utf8(bytes)
+        .map_err(|err| invalid_encoding(err.as_bytes(), err.utf8_error(), path))?;
+    let corpus::Entries { spans, weights } = format.split(&text, path)?;
+
+    Ok(Corpus::from_text(text, spans).with_weights(weights))
+}
+
+/// Describes a corpus that is not valid UTF-8, locating the line of the first
+/// invalid byte.
+fn invalid_encoding(bytes: &[u8], source: Utf8Error, path: Option<&Path>) -> FuzzySearchError {
+    let valid = &bytes[..source.valid_up_to()];
+    let line = valid.iter().filter(|&&byte| byte == b'\n').count() + 1;
+
+    FuzzySearchError::InvalidCorpusEncoding {
+        path: path.map(Path::to_path_buf),
+        line,
+        source,
+    }
+}
+
+/// A match for a query found in the corpus.
+///
+/// The matched text is borrowed from the searcher's corpus, so no allocation
+/// is needed per result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match<'a> {
+    /// The matching corpus string.
+    pub text: &'a str,
+    /// The position of the matching string in the corpus.
+    pub index: usize,
+    /// The raw distance between the query and the matching string, as
+    /// measured by the searcher's metric.
+    pub distance: f64,
+    /// The correctness of the match, ranging from 0 (completely incorrect) to
+    /// 1 (completely correct).
+    pub score: f64,
+    /// The weight of the matching string, which is 1 unless the corpus is
+    /// weighted.
+    pub weight: f64,
+}
+
+/// A bound on how close a corpus string must be to a query to be returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    /// Match strings whose raw distance from the query is at most this value,
+    /// such as a maximum number of edits.
+    MaxDistance(f64),
+    /// Match strings whose score is at least this value, between 0 and 1.
+    MinScore(f64),
+}
+
+impl Threshold {
+    fn accepts(&self, candidate: &Candidate) -> bool {
+        match *self {
+            Threshold::MaxDistance(max_distance) => candidate.distance <= max_distance,
+            Threshold::MinScore(min_score) => candidate.score >= min_score,
+        }
+    }
+}
 
-    // Create a vector to store the correctness of each string in the corpus.
-    let mut correctness = vec![0f64; reference_strs.len()];
+/// A scored corpus string, ordered so that better matches compare as less.
+///
+/// Candidates are ranked by ascending distance. Ties are broken by the higher
+/// score and then by the lower corpus index, so rankings are deterministic.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    index: usize,
+    distance: f64,
+    score: f64,
+}
 
-    for (idx, reference_str) in reference_strs.iter().enumerate() {
-        // Calculate the distance between the argument string and the current corpus string.
-        let distance = distance(arg, reference_str);
+impl Candidate {
+    fn new(arg: &str, reference_str: &str, index: usize, metric: &dyn Metric) -> Self {
+        let distance = metric.distance(arg, reference_str);
 
-        // Store the correctness of the current corpus string.
-        correctness[idx] = 1.0 - (distance as f64 / arg.len() as f64);
+        Self {
+            index,
+            distance,
+            score: metric.similarity_from_distance(arg, reference_str, distance),
+        }
+    }
 
-        // Update the closest string if the current corpus string is closer than the current closest string.
-        if distance < closest_distance {
-            closest_str = reference_str;
-            closest_distance = distance;
+    /// Scores a corpus string if it is within `max_distance` of `arg`, without
+    /// computing its score otherwise.
+    fn within(
+        arg: &str,
+        reference_str: &str,
+        index: usize,
+        metric: &dyn Metric,
+        max_distance: f64,
+    ) -> Option<Self> {
+        let distance = metric.distance_within(arg, reference_str, max_distance)?;
+
+        Some(Self {
+            index,
+            distance,
+            score: metric.similarity_from_distance(arg, reference_str, distance),
+        })
+    }
+
+    fn into_match(self, corpus: &Corpus) -> Match<'_> {
+        Match {
+            text: &corpus[self.index],
+            index: self.index,
+            distance: self.distance,
+            score: self.score,
+            weight: corpus.weight(self.index).unwrap_or(1.0),
         }
     }
+}
 
-    // PERFORMANCE: Can be optimized to avoid cloning?
-    closest_str.clone()
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
 }
 
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then_with(|| other.score.total_cmp(&self.score))
+            .then_with(|| self.index.cmp(&other.index))
+    }
+}
+
+/// A candidate with its blended rank, ordered so that better matches compare
+/// as less.
+///
+/// Candidates are ranked by ascending rank. Ties are broken by the higher
+/// weight and then in the usual candidate order.
+#[derive(Debug, Clone, Copy)]
+struct Ranked {
+    rank: f64,
+    weight: f64,
+    candidate: Candidate,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank
+            .total_cmp(&other.rank)
+            .then_with(|| other.weight.total_cmp(&self.weight))
+            .then_with(|| self.candidate.cmp(&other.candidate))
+    }
+}
+
+/// The number of corpus strings scored together by a linear scan.
+///
+/// With the `parallel` feature, each chunk is scored on its own thread and
+/// the per-chunk results are merged.
+const SCAN_CHUNK_SIZE: usize = 4096;
+
+/// Scores the corpus chunk by chunk with `map`, which receives the corpus
+/// index of the chunk's first string, and combines the results with `merge`.
+///
+/// Chunks are scored in parallel when the `parallel` feature is enabled.
+/// Since [`Candidate`] ranks every pair of corpus strings strictly, merging
+/// gives the same results in any order.
+fn reduce_chunks<T: Default + Send>(
+    reference_strs: &Corpus,
+    map: impl Fn(usize, corpus::Iter<'_>) -> T + Send + Sync,
+    merge: impl Fn(T, T) -> T + Send + Sync,
+) -> T {
+    let chunks = 0..reference_strs.len().div_ceil(SCAN_CHUNK_SIZE);
+    let map_chunk = |chunk_index: usize| {
+        let start = chunk_index * SCAN_CHUNK_SIZE;
+        map(start, chunk(reference_strs, start))
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        chunks
+            .into_par_iter()
+            .map(map_chunk)
+            .reduce(T::default, merge)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        chunks.map(map_chunk).fold(T::default(), merge)
+    }
+}
+
+/// Returns the strings of the chunk starting at corpus index `start`.
+fn chunk(reference_strs: &Corpus, start: usize) -> corpus::Iter<'_> {
+    reference_strs.range(start..(start + SCAN_CHUNK_SIZE).min(reference_strs.len()))
+}
+
+/// Finds the string in the corpus closest to the given string.
+///
+/// Closeness is measured by the given `metric`. The correctness of each
+/// string is the metric's similarity, which ranges from 0 (completely
+/// incorrect) to 1 (completely correct).
+///
+/// Returns `None` if the corpus is empty.
+fn find_closest_str(arg: &str, reference_strs: &Corpus, metric: &dyn Metric) -> Option<Candidate> {
+    reduce_chunks(
+        reference_strs,
+        |offset, chunk| {
+            let mut closest: Option<Candidate> = None;
+
+            for (idx, reference_str) in chunk.enumerate() {
+                // Score the current corpus string against the argument string,
+                // skipping it once it is known to be further than the closest
+                // string. Equally distant strings can still win on score.
+                let candidate = match closest {
+                    None => Candidate::new(arg, reference_str, offset + idx, metric),
+                    Some(closest) => match Candidate::within(
+                        arg,
+                        reference_str,
+                        offset + idx,
+                        metric,
+                        closest.distance,
+                    ) {
+                        Some(candidate) => candidate,
+                        None => continue,
+                    },
+                };
+
+                // Update the closest string if the current corpus string is closer than the current closest string.
+                if closest.is_none_or(|closest| candidate < closest) {
+                    closest = Some(candidate);
+                }
+            }
+
+            closest
+        },
+        |a, b| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        },
+    )
+}
+
+/// Finds the `k` strings in the corpus closest to the given string.
+///
+/// Results are sorted from best to worst match, as ranked by [`Candidate`].
+fn find_top_k(arg: &str, reference_strs: &Corpus, metric: &dyn Metric, k: usize) -> Vec<Candidate> {
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let best = reduce_chunks(
+        reference_strs,
+        |offset, chunk| {
+            // Keep the best `k` candidates seen so far in a max-heap, so the
+            // worst of them is always on top and can be evicted cheaply.
+            let mut best: BinaryHeap<Candidate> = BinaryHeap::with_capacity(k + 1);
+
+            for (idx, reference_str) in chunk.enumerate() {
+                // Once `k` candidates are kept, strings further than the worst
+                // of them can be rejected without being fully scored.
+                let candidate = match best.peek() {
+                    Some(worst) if best.len() == k => {
+                        Candidate::within(arg, reference_str, offset + idx, metric, worst.distance)
+                    }
+                    _ => Some(Candidate::new(arg, reference_str, offset + idx, metric)),
+                };
+                let Some(candidate) = candidate else {
+                    continue;
+                };
+
+                best.push(candidate);
+                if best.len() > k {
+                    best.pop();
+                }
+            }
+
+            best
+        },
+        |mut a, mut b| {
+            a.append(&mut b);
+            while a.len() > k {
+                a.pop();
+            }
+            a
+        },
+    );
+
+    best.into_sorted_vec()
+}
+
+/// Finds every string in the corpus that satisfies the given threshold.
+///
+/// Results are sorted from best to worst match, as ranked by [`Candidate`].
+fn find_within(
+    arg: &str,
+    reference_strs: &Corpus,
+    metric: &dyn Metric,
+    threshold: Threshold,
+) -> Vec<Candidate> {
+    let mut matches = reduce_chunks(
+        reference_strs,
+        |offset, chunk| {
+            chunk
+                .enumerate()
+                .filter_map(|(idx, reference_str)| match threshold {
+                    Threshold::MaxDistance(max_distance) => {
+                        Candidate::within(arg, reference_str, offset + idx, metric, max_distance)
+                    }
+                    Threshold::MinScore(_) => {
+                        Some(Candidate::new(arg, reference_str, offset + idx, metric))
+                            .filter(|candidate| threshold.accepts(candidate))
+                    }
+                })
+                .collect()
+        },
+        |mut a: Vec<Candidate>, mut b| {
+            a.append(&mut b);
+            a
+        },
+    );
+
+    matches.sort_unstable();
+    matches
+}
+
+/// Searches a corpus of strings for the closest matches to a query.
 pub struct FuzzySearcher {
-    corpus: Vec<String>,
+    corpus: Corpus,
+    /// The normalized form of each corpus string, or `None` if the normalizer
+    /// leaves text unchanged.
+    keys: Option<Corpus>,
+    normalizer: Normalizer,
+    preserve_case: bool,
+    metric: Box<dyn Metric>,
+    index: Option<Index>,
+    /// How distances are combined with corpus weights, or `None` to rank by
+    /// distance alone.
+    blend: Option<Box<dyn Blend>>,
+    /// The largest weight in the corpus, which bounds how far away a string
+    /// that outranks a closer one can be.
+    max_weight: f64,
 }
 
 impl FuzzySearcher {
     /// Creates a new `FuzzySearcher` instance by loading a corpus from a specified file path.
     ///
     /// The corpus is expected to be a newline-separated file containing words or strings.
+    /// Blank lines and a leading byte order mark are skipped, and `\r\n` line
+    /// endings are accepted. Use [`CorpusFormat`] to skip comments or duplicate
+    /// entries.
     ///
     /// # Arguments
     ///
@@ -123,19 +1195,549 @@ impl FuzzySearcher {
     ///
     /// # Errors
     ///
-    /// Returns `FuzzySearchError` if the corpus file cannot be opened or read.
-
+    /// Returns `FuzzySearchError` if the corpus file cannot be opened or read,
+    /// or exceeds 4 GiB.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, FuzzySearchError> {
-        let corpus_path = path::Path::new("corpus/words.txt");
-        let corpus = load_corpus(corpus_path)?;
+        let corpus = load_corpus(path, CorpusFormat::default())?;
+
+        Ok(Self::from_corpus(corpus))
+    }
+
+    /// Creates a new `FuzzySearcher` instance by memory-mapping a corpus file.
+    ///
+    /// The file is in the same format as the file loaded by
+    /// [`FuzzySearcher::new`], but rather than being read into memory, it is
+    /// mapped and its pages are shared with every other process mapping the
+    /// same file. Only the position of each line is computed when it is
+    /// opened. Files that cannot be mapped are read into memory instead.
+    ///
+    /// # Safety
+    ///
+    /// The corpus file must not be modified or truncated while the searcher
+    /// exists, including by other processes. Doing so is undefined behavior.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError` if the corpus file cannot be opened or read,
+    /// is not valid UTF-8, or exceeds 4 GiB.
+    pub unsafe fn new_mapped<P: AsRef<Path>>(path: P) -> Result<Self, FuzzySearchError> {
+        // SAFETY: the caller guarantees that the file is not modified while
+        // the searcher exists.
+        let corpus = unsafe { map_corpus(path, CorpusFormat::default())? };
+
+        Ok(Self::from_corpus(corpus))
+    }
+
+    /// Creates a new `FuzzySearcher` instance from an in-memory corpus.
+    ///
+    /// Each string is a separate corpus entry, and keeps its position as its
+    /// index in search results.
+    pub fn from_strings(corpus: Vec<String>) -> Self {
+        Self::from_corpus(corpus.iter().collect())
+    }
+
+    /// Creates a new `FuzzySearcher` instance from a [`Corpus`].
+    pub fn from_corpus(corpus: Corpus) -> Self {
+        Self {
+            max_weight: corpus.max_weight(),
+            corpus,
+            keys: None,
+            normalizer: Normalizer::new(),
+            preserve_case: false,
+            metric: Box::new(Levenshtein::new()),
+            index: None,
+            blend: None,
+        }
+    }
+
+    /// Creates a new `FuzzySearcher` instance by reading a corpus from a reader.
+    ///
+    /// The reader is expected to produce newline-separated lines of text, in the
+    /// same format as the file read by [`FuzzySearcher::new`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError` if the reader fails, does not produce valid UTF-8,
+    /// or produces more than 4 GiB.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, FuzzySearchError> {
+        let corpus = read_corpus(reader, None, CorpusFormat::default())?;
+
+        Ok(Self::from_corpus(corpus))
+    }
 
-        Ok(Self { corpus })
+    /// Replaces the metric used to compare queries against the corpus.
+    ///
+    /// Searchers use [`Levenshtein`] distance unless configured otherwise.
+    /// Any index is rebuilt for the new metric, or removed if it cannot be
+    /// built for the new metric.
+    pub fn with_metric<M: Metric + 'static>(mut self, metric: M) -> Self {
+        self.metric = Box::new(metric);
+        self.rebuild_index();
+        self
+    }
+
+    /// Ranks matches by blending their distance with the weight of their
+    /// corpus string, such as a word frequency loaded with
+    /// [`CorpusFormat::with_weights`].
+    ///
+    /// Matches are sorted by ascending [`Blend::rank`], with ties broken by
+    /// the higher weight and then as usual. With [`ByDistance`], weights only
+    /// break ties between equally distant matches; with [`LogWeight`], a much
+    /// more frequent string can outrank a closer one.
+    ///
+    /// [`FuzzySearcher::search`] and [`FuzzySearcher::search_top_k`] first
+    /// find the closest strings as usual, then compare the query against every
+    /// string within the [`Blend::max_distance`] at which a heavier string
+    /// could still outrank them, using the index where it can. Only the order
+    /// of [`FuzzySearcher::search_within`] results changes.
+    pub fn with_ranking<B: Blend + 'static>(mut self, blend: B) -> Self {
+        self.blend = Some(Box::new(blend));
+        self
+    }
+
+    /// Builds an index over the corpus, which subsequent searches use
+    /// instead of comparing the query against every corpus string.
+    ///
+    /// Indexed searches return exactly the same results as a linear scan.
+    /// Searches the index cannot answer, such as
+    /// [`Threshold::MinScore`] searches, fall back to a linear scan.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::IncompatibleIndex` if this kind of index
+    /// cannot be built for the searcher's metric.
+    pub fn with_index(mut self, kind: IndexKind) -> Result<Self, FuzzySearchError> {
+        self.index = Some(Index::build(kind, self.keys(), self.metric.as_ref())?);
+        Ok(self)
+    }
+
+    /// Saves the searcher's index to a file, so that later searchers over the
+    /// same corpus can load it with [`FuzzySearcher::load_index`] or
+    /// [`FuzzySearcher::load_index_mapped`] instead of building it again.
+    ///
+    /// The file format is versioned, and records fingerprints of the
+    /// normalized corpus and of the metric that the index was built for.
+    ///
+    /// # File format
+    ///
+    /// Index files start with a fixed 48-byte header, followed by a payload
+    /// specific to the kind of index. All integers are little-endian.
+    ///
+    /// | Offset | Size | Field                                                  |
+    /// |--------|------|--------------------------------------------------------|
+    /// | 0      | 8    | Magic bytes, `FZSINDEX`                                |
+    /// | 8      | 4    | Format version, currently 2                            |
+    /// | 12     | 1    | Index kind: 0 BK-tree, 1 SymSpell, 2 Levenshtein automaton, 3 n-gram |
+    /// | 13     | 3    | Reserved, zero                                         |
+    /// | 16     | 8    | Fingerprint of the normalized corpus                   |
+    /// | 24     | 8    | Fingerprint of the metric                              |
+    /// | 32     | 8    | Payload length in bytes                                |
+    /// | 40     | 8    | Checksum of the payload                                |
+    ///
+    /// Files with reserved bytes set are rejected, so that later versions can
+    /// use them.
+    ///
+    /// Fingerprints and checksums use a custom 64-bit hash, a word-wise
+    /// variant of FNV-1a. Starting from the FNV-1a offset basis
+    /// `0xcbf29ce484222325`, each 8-byte word of the input is read as a
+    /// `u64`, XORed into the hash, and the hash multiplied by the FNV prime
+    /// `0x100000001b3`, wrapping on overflow. Any bytes left after the last
+    /// whole word are then mixed in the same way, one at a time. The corpus
+    /// fingerprint hashes the number of corpus strings as a `u64`, then each
+    /// string's length in bytes as a `u64` followed by its bytes, as
+    /// normalized by the searcher. The metric fingerprint hashes the bits of
+    /// the distances the metric measures between a fixed set of probe
+    /// strings, so an index is only loaded by a searcher that compares
+    /// strings the same way as the one that saved it. The checksum hashes the
+    /// payload.
+    ///
+    /// Payloads are made of scalar fields and tables. Scalars are stored as
+    /// `u64`, or as the bits of an `f64`. A table is its number of entries as
+    /// a `u64`, followed by each entry as a fixed number of `u32` words, so
+    /// that indexes read their tables in place rather than decoding them:
+    ///
+    /// - BK-tree: a table of nodes, each its corpus index, the position of
+    ///   its first edge and its number of edges, then a table of edges, each
+    ///   the child's node number and the low and high halves of the bits of
+    ///   its distance.
+    /// - SymSpell: the maximum distance, the prefix length, then the
+    ///   postings.
+    /// - Levenshtein automaton: the length of the longest key, a table of the
+    ///   keys' corpus indexes in sorted key order, a table of nodes, each the
+    ///   position of its first edge, its number of edges, the position of its
+    ///   first key and its number of keys, then a table of edges, each its
+    ///   character as a scalar value and the child's node number.
+    /// - n-gram: the gram size, the minimum overlap, then the postings.
+    ///
+    /// Postings are a table of entries, each a hash and a corpus index,
+    /// sorted by hash and then index. The hashes are 32-bit FNV-1a hashes of
+    /// the little-endian scalar values of the characters of a SymSpell
+    /// delete or an n-gram.
+    ///
+    /// Nodes are numbered by their position in their table, and the root is
+    /// the first node. Each node's edges follow those of the node before it,
+    /// and lead to nodes after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::NoIndex` if the searcher has no index, or
+    /// `FuzzySearchError::UnableToAccessIndexFile` if the file cannot be
+    /// written.
+    pub fn save_index<P: AsRef<Path>>(&self, path: P) -> Result<(), FuzzySearchError> {
+        let path = path.as_ref();
+        let index = self.index.as_ref().ok_or(FuzzySearchError::NoIndex)?;
+
+        std::fs::write(path, index.to_bytes(self.keys(), self.metric.as_ref())).map_err(|source| {
+            FuzzySearchError::UnableToAccessIndexFile {
+                path: path.to_path_buf(),
+                source,
+            }
+        })
+    }
+
+    /// Loads an index saved by [`FuzzySearcher::save_index`], which
+    /// subsequent searches use as if it had been built with
+    /// [`FuzzySearcher::with_index`].
+    ///
+    /// The searcher must have the same corpus, normalizer and metric as the
+    /// searcher that saved the index. Configure the normalizer and metric
+    /// before loading the index, since changing them afterwards rebuilds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::UnableToAccessIndexFile` if the file cannot
+    /// be read, or `FuzzySearchError::InvalidIndexFile` if it is corrupted,
+    /// in an unsupported format, or was saved for a different corpus or
+    /// metric.
+    pub fn load_index<P: AsRef<Path>>(self, path: P) -> Result<Self, FuzzySearchError> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).map_err(|source| FuzzySearchError::UnableToAccessIndexFile {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        self.install_index(IndexBytes::Read(bytes), path)
+    }
+
+    /// Loads an index saved by [`FuzzySearcher::save_index`] by
+    /// memory-mapping the file, as [`FuzzySearcher::load_index`] does by
+    /// reading it.
+    ///
+    /// The index is searched in place in the mapped file, so its pages are
+    /// shared with every other process mapping the same file, and loading it
+    /// only checks that it is consistent with the corpus. Files that cannot
+    /// be mapped are read into memory instead.
+    ///
+    /// # Safety
+    ///
+    /// The index file must not be modified or truncated while the searcher
+    /// exists, including by other processes. Doing so is undefined behavior.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::UnableToAccessIndexFile` if the file cannot
+    /// be opened or read, or `FuzzySearchError::InvalidIndexFile` if it is
+    /// corrupted, in an unsupported format, or was saved for a different
+    /// corpus or metric.
+    pub unsafe fn load_index_mapped<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> Result<Self, FuzzySearchError> {
+        let path = path.as_ref();
+        let access_error = |source| FuzzySearchError::UnableToAccessIndexFile {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let mut index_file = std::fs::File::open(path).map_err(access_error)?;
+
+        // SAFETY: the caller guarantees that the file is not modified while
+        // the searcher exists.
+        let bytes = match unsafe { Mmap::map(&index_file) } {
+            Ok(mmap) => IndexBytes::Mapped(mmap),
+            Err(_) => {
+                let mut bytes = Vec::new();
+                index_file.read_to_end(&mut bytes).map_err(access_error)?;
+                IndexBytes::Read(bytes)
+            }
+        };
+
+        self.install_index(bytes, path)
+    }
+
+    /// Replaces the searcher's index with the one stored in `bytes`, read
+    /// from the index file at `path`.
+    fn install_index(mut self, bytes: IndexBytes, path: &Path) -> Result<Self, FuzzySearchError> {
+        let index =
+            Index::from_bytes(bytes, self.keys(), self.metric.as_ref()).map_err(|reason| {
+                FuzzySearchError::InvalidIndexFile {
+                    path: path.to_path_buf(),
+                    reason,
+                }
+            })?;
+        self.index = Some(index);
+        Ok(self)
+    }
+
+    /// Rebuilds the index, if any, after the keys or metric have changed.
+    fn rebuild_index(&mut self) {
+        if let Some(kind) = self.index.take().map(|index| index.kind()) {
+            self.index = Index::build(kind, self.keys(), self.metric.as_ref()).ok();
+        }
+    }
+
+    /// Replaces the normalizer applied to the corpus and to every query.
+    ///
+    /// The corpus is normalized immediately, so that each search only needs
+    /// to normalize its query. Search results still return the original,
+    /// unnormalized corpus text.
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self.keys = (!normalizer.is_identity()).then(|| {
+            self.corpus
+                .iter()
+                .map(|text| normalizer.normalize(text))
+                .collect()
+        });
+        self.rebuild_index();
+        self
+    }
+
+    /// Enables or disables case-insensitive matching.
+    ///
+    /// This toggles full case folding in the searcher's [`Normalizer`], so
+    /// that "Hello", "hello" and "HELLO" are all zero edits apart.
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        let normalizer = self.normalizer.with_case_fold(case_insensitive);
+        self.with_normalizer(normalizer)
+    }
+
+    /// Enables or disables copying the query's casing onto corrections
+    /// returned by [`FuzzySearcher::correct`].
+    ///
+    /// With this enabled, correcting "Helo" yields "Hello" and correcting
+    /// "HELO" yields "HELLO", whichever case the corpus stores the word in.
+    /// See [`match_case`] for how casing patterns are copied.
+    pub fn with_preserve_case(mut self, preserve_case: bool) -> Self {
+        self.preserve_case = preserve_case;
+        self
+    }
+
+    /// Returns the corpus being searched, in its original, unnormalized form.
+    pub fn corpus(&self) -> &Corpus {
+        &self.corpus
+    }
+
+    /// Returns the correction for the given argument string: the closest
+    /// string in the corpus, recased to match the argument string if
+    /// [`FuzzySearcher::with_preserve_case`] is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`FuzzySearcher::search`].
+    pub fn correct(&self, arg: &str) -> Result<String, FuzzySearchError> {
+        let closest = self.search(arg)?;
+
+        if self.preserve_case {
+            Ok(match_case(arg, closest.text))
+        } else {
+            Ok(closest.text.to_string())
+        }
     }
 
     /// Searches the corpus for the string closest to the given argument string.
     ///
     /// Returns the closest string from the corpus.
-    pub fn search(&self, arg: &str) -> String {
-        find_closest_str(arg, &self.corpus)
+    ///
+    /// # Errors
+    ///
+    /// Returns `FuzzySearchError::InvalidQuery` if the argument string is empty,
+    /// or `FuzzySearchError::EmptyCorpus` if there is nothing to search.
+    pub fn search(&self, arg: &str) -> Result<Match<'_>, FuzzySearchError> {
+        let arg = self.prepare(arg)?;
+        let closest = if let Some(blend) = &self.blend {
+            self.blended_top_k(&arg, blend.as_ref(), 1).pop()
+        } else {
+            self.index_top_k(&arg, 1)
+                .and_then(|mut candidates| candidates.pop())
+                .or_else(|| find_closest_str(&arg, self.keys(), self.metric.as_ref()))
+        };
+
+        closest
+            .map(|candidate| candidate.into_match(&self.corpus))
+            .ok_or(FuzzySearchError::EmptyCorpus)
+    }
+
+    /// Searches the corpus for the `k` strings closest to the given argument
+    /// string.
+    ///
+    /// Results are sorted from best to worst match: by ascending distance,
+    /// with ties broken by the higher score and then by the lower corpus
+    /// index, unless a ranking is configured with
+    /// [`FuzzySearcher::with_ranking`]. Fewer than `k` results are returned if
+    /// the corpus is smaller than `k`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`FuzzySearcher::search`].
+    pub fn search_top_k(&self, arg: &str, k: usize) -> Result<Vec<Match<'_>>, FuzzySearchError> {
+        let arg = self.prepare(arg)?;
+        let candidates = if let Some(blend) = &self.blend {
+            self.blended_top_k(&arg, blend.as_ref(), k)
+        } else {
+            self.index_top_k(&arg, k)
+                .unwrap_or_else(|| find_top_k(&arg, self.keys(), self.metric.as_ref(), k))
+        };
+
+        Ok(self.to_matches(candidates))
+    }
+
+    /// Searches the corpus for every string within the given threshold of the
+    /// argument string.
+    ///
+    /// Results are sorted in the same order as [`FuzzySearcher::search_top_k`].
+    /// An empty vector means that nothing in the corpus is close enough.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`FuzzySearcher::search`].
+    pub fn search_within(
+        &self,
+        arg: &str,
+        threshold: Threshold,
+    ) -> Result<Vec<Match<'_>>, FuzzySearchError> {
+        let arg = self.prepare(arg)?;
+        let mut candidates = self.find_within(&arg, threshold);
+        if let Some(blend) = &self.blend {
+            let mut ranked: Vec<Ranked> = candidates
+                .into_iter()
+                .map(|candidate| self.ranked(candidate, blend.as_ref()))
+                .collect();
+            ranked.sort_unstable();
+            candidates = ranked.into_iter().map(|ranked| ranked.candidate).collect();
+        }
+
+        Ok(self.to_matches(candidates))
+    }
+
+    /// Finds every candidate within the threshold, using the index if it can
+    /// answer the search exactly.
+    fn find_within(&self, arg: &str, threshold: Threshold) -> Vec<Candidate> {
+        let indexed = match (&self.index, threshold) {
+            (Some(index), Threshold::MaxDistance(max_distance)) => {
+                index.within_distance(arg, self.keys(), self.metric.as_ref(), max_distance)
+            }
+            _ => None,
+        };
+
+        indexed.unwrap_or_else(|| find_within(arg, self.keys(), self.metric.as_ref(), threshold))
+    }
+
+    /// Finds the `k` best candidates using the index, or returns `None` if
+    /// there is no index or it cannot answer the search exactly.
+    fn index_top_k(&self, arg: &str, k: usize) -> Option<Vec<Candidate>> {
+        self.index
+            .as_ref()?
+            .top_k(arg, self.keys(), self.metric.as_ref(), k)
+    }
+
+    /// Finds the `k` best candidates by blended rank.
+    ///
+    /// The `k` closest candidates are ranked first. Any candidate that
+    /// outranks the worst of them must be within the blend's maximum distance
+    /// for that rank, so only those candidates are compared.
+    fn blended_top_k(&self, arg: &str, blend: &dyn Blend, k: usize) -> Vec<Candidate> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let closest = self
+            .index_top_k(arg, k)
+            .unwrap_or_else(|| find_top_k(arg, self.keys(), self.metric.as_ref(), k));
+        let worst_rank = match closest.len() {
+            len if len == k => closest
+                .iter()
+                .map(|&candidate| self.ranked(candidate, blend).rank)
+                .fold(f64::NEG_INFINITY, f64::max),
+            _ => f64::INFINITY,
+        };
+
+        let max_distance = blend.max_distance(worst_rank, self.max_weight);
+        let mut best: BinaryHeap<Ranked> = BinaryHeap::with_capacity(k + 1);
+        for candidate in self.find_within(arg, Threshold::MaxDistance(max_distance)) {
+            best.push(self.ranked(candidate, blend));
+            if best.len() > k {
+                best.pop();
+            }
+        }
+
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|ranked| ranked.candidate)
+            .collect()
+    }
+
+    /// Ranks a candidate by blending its distance with its weight.
+    fn ranked(&self, candidate: Candidate, blend: &dyn Blend) -> Ranked {
+        let weight = self.corpus.weight(candidate.index).unwrap_or(1.0);
+
+        Ranked {
+            rank: blend.rank(candidate.distance, weight),
+            weight,
+            candidate,
+        }
+    }
+
+    /// Returns the strings that queries are compared against: the normalized
+    /// corpus, or the corpus itself if no normalization is configured.
+    fn keys(&self) -> &Corpus {
+        self.keys.as_ref().unwrap_or(&self.corpus)
+    }
+
+    /// Normalizes the argument string and checks that it can be searched for
+    /// in the corpus.
+    ///
+    /// Empty queries are rejected because they are equally far from every
+    /// string of the same length, so no match would be meaningful. This
+    /// includes queries that only become empty after normalization.
+    fn prepare<'q>(&self, arg: &'q str) -> Result<Cow<'q, str>, FuzzySearchError> {
+        let arg = self.normalizer.normalize(arg);
+        if arg.is_empty() {
+            return Err(FuzzySearchError::InvalidQuery {
+                reason: "query is empty",
+            });
+        }
+
+        if self.corpus.is_empty() {
+            return Err(FuzzySearchError::EmptyCorpus);
+        }
+
+        Ok(arg)
+    }
+
+    /// Converts ranked candidates into matches borrowing the original corpus text.
+    fn to_matches(&self, candidates: Vec<Candidate>) -> Vec<Match<'_>> {
+        candidates
+            .into_iter()
+            .map(|candidate| candidate.into_match(&self.corpus))
+            .collect()
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for FuzzySearcher {
+    /// Creates a new `FuzzySearcher` instance with one corpus entry per item.
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        Self::from_corpus(iter.into_iter().collect())
+    }
+}
+
+impl FromStr for FuzzySearcher {
+    type Err = FuzzySearchError;
+
+    /// Creates a new `FuzzySearcher` instance from newline-separated corpus
+    /// text, such as a corpus embedded with `include_str!`.
+    fn from_str(corpus: &str) -> Result<Self, Self::Err> {
+        CorpusFormat::default().parse(corpus).map(Self::from_corpus)
     }
 }
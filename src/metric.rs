@@ -0,0 +1,1298 @@
+//! Distance metrics used to compare a query against corpus strings.
+//!
+//! Every metric implements the [`Metric`] trait, which lets a
+//! [`FuzzySearcher`](crate::FuzzySearcher) be constructed with whichever
+//! notion of "closeness" best fits the data being searched.
+//!
+//! The built-in metrics compare strings one [`Unit`] at a time. By default a
+//! unit is a Unicode scalar value; metrics can instead be configured to
+//! compare extended grapheme clusters, so that a character written with
+//! combining marks counts as a single unit.
+
+use std::{collections::HashMap, hash::Hash};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A way of measuring how far apart two strings are.
+///
+/// Implementations return a raw `distance`, where `0.0` means the strings are
+/// identical, and a `similarity` normalized between 0 (completely incorrect)
+/// and 1 (completely correct). Searches rank corpus strings by ascending
+/// distance.
+pub trait Metric: Send + Sync {
+    /// Returns the raw distance between `a` and `b`.
+    fn distance(&self, a: &str, b: &str) -> f64;
+
+    /// Returns the similarity between `a` and `b`, ranging from 0 to 1.
+    ///
+    /// The default implementation normalizes [`Metric::distance`] by the
+    /// length of the longer string, in characters.
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        normalized_similarity(self.distance(a, b), a.chars().count(), b.chars().count())
+    }
+
+    /// Returns the similarity between `a` and `b`, given the `distance`
+    /// between them returned by [`Metric::distance`].
+    ///
+    /// Searches use this to score a corpus string without comparing it twice.
+    /// The default implementation ignores `distance` and calls
+    /// [`Metric::similarity`].
+    fn similarity_from_distance(&self, a: &str, b: &str, distance: f64) -> f64 {
+        let _ = distance;
+        self.similarity(a, b)
+    }
+
+    /// Returns the distance between `a` and `b` if it is at most
+    /// `max_distance`, or `None` if it is larger.
+    ///
+    /// Searches use this to reject corpus strings that cannot beat the
+    /// matches found so far, so implementations may stop as soon as the
+    /// distance is known to exceed `max_distance`. The default implementation
+    /// computes the full [`Metric::distance`].
+    fn distance_within(&self, a: &str, b: &str, max_distance: f64) -> Option<f64> {
+        let distance = self.distance(a, b);
+        (distance <= max_distance).then_some(distance)
+    }
+
+    /// Returns `true` if [`Metric::distance`] is symmetric, finite and
+    /// satisfies the triangle inequality: `distance(a, c) <= distance(a, b) +
+    /// distance(b, c)` for all strings.
+    ///
+    /// Indexes such as BK-trees rely on this to skip parts of the corpus, and
+    /// can only be built for metrics that return `true`. The default
+    /// implementation conservatively returns `false`.
+    fn satisfies_triangle_inequality(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if any two strings within distance `d` of each other
+    /// can be made equal by deleting at most `d` characters from each.
+    ///
+    /// This holds for edit distances that count characters, and is what
+    /// deletion-based indexes such as SymSpell rely on to find every string
+    /// within a maximum distance. It does not hold for edit distances that
+    /// count [`Unit::Graphemes`], since deleting one grapheme cluster can
+    /// delete several characters. The default implementation conservatively
+    /// returns `false`.
+    fn bounded_by_deletions(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if [`Metric::distance`] is never less than the
+    /// Levenshtein distance between the same strings, counted in characters.
+    ///
+    /// Levenshtein automata rely on this to find every string within a
+    /// maximum distance. The default implementation conservatively returns
+    /// `false`.
+    fn bounded_by_levenshtein(&self) -> bool {
+        false
+    }
+}
+
+/// The unit of text that the built-in metrics compare and count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Unicode scalar values, i.e. `char`s.
+    #[default]
+    Chars,
+    /// Extended grapheme clusters, i.e. user-perceived characters.
+    Graphemes,
+}
+
+impl Unit {
+    /// Returns the number of units in `text`.
+    fn count(self, text: &str) -> usize {
+        match self {
+            Unit::Chars => text.chars().count(),
+            Unit::Graphemes => text.graphemes(true).count(),
+        }
+    }
+
+    /// Converts an edit distance between `a` and `b` into a similarity by
+    /// normalizing it by the length of the longer string, in units.
+    ///
+    /// This implements [`Metric::similarity_from_distance`] for every
+    /// built-in metric that counts edits.
+    fn similarity_from_distance(self, a: &str, b: &str, distance: f64) -> f64 {
+        normalized_similarity(distance, self.count(a), self.count(b))
+    }
+}
+
+/// Splits `$a` and `$b` into slices of the given [`Unit`] and evaluates
+/// `$body` with them bound to `$x` and `$y`.
+macro_rules! with_units {
+    ($unit:expr, $a:expr, $b:expr, |$x:ident, $y:ident| $body:expr) => {
+        match $unit {
+            // An ASCII character is a single byte, so ASCII strings are
+            // compared byte by byte without collecting their characters.
+            Unit::Chars if $a.is_ascii() && $b.is_ascii() => {
+                let $x: &[u8] = $a.as_bytes();
+                let $y: &[u8] = $b.as_bytes();
+                $body
+            }
+            Unit::Chars => {
+                let $x: &[char] = &$a.chars().collect::<Vec<_>>();
+                let $y: &[char] = &$b.chars().collect::<Vec<_>>();
+                $body
+            }
+            Unit::Graphemes => {
+                let $x: &[&str] = &$a.graphemes(true).collect::<Vec<_>>();
+                let $y: &[&str] = &$b.graphemes(true).collect::<Vec<_>>();
+                $body
+            }
+        }
+    };
+}
+
+/// Converts a distance into a similarity by normalizing it by the length of
+/// the longer string, in units.
+fn normalized_similarity(distance: f64, a_len: usize, b_len: usize) -> f64 {
+    let max_len = a_len.max(b_len);
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    1.0 - (distance / max_len as f64).min(1.0)
+}
+
+/// Computes the Levenshtein edit distance between two strings.
+///
+/// The distance is the minimum number of single-character insertions,
+/// deletions and substitutions required to transform `a` into `b`.
+/// Characters are compared as Unicode scalar values; use [`Levenshtein`]
+/// with [`Unit::Graphemes`] to compare grapheme clusters instead.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    with_units!(Unit::Chars, a, b, |a, b| levenshtein_units(a, b))
+}
+
+fn levenshtein_units<T: PatternUnit>(a: &[T], b: &[T]) -> usize {
+    // Levenshtein distance is symmetric, so the shorter string is encoded as
+    // the bit-vector pattern and the longer one is scanned.
+    let (pattern, text) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+
+    match pattern.len() {
+        0 => text.len(),
+        1..=64 => myers(pattern, text),
+        _ => myers_blocks(pattern, text),
+    }
+}
+
+/// Computes the Levenshtein distance between `a` and `b` if it is at most
+/// `max_distance`.
+///
+/// Strings whose lengths differ by more than `max_distance` are rejected
+/// without comparing them. Otherwise, when the bound is smaller than the
+/// strings, only the diagonal band of the dynamic programming matrix within
+/// `max_distance` of the main diagonal is computed, and the computation stops
+/// as soon as every cell of a row exceeds the bound (Ukkonen's cutoff).
+fn levenshtein_within_units<T: PatternUnit>(a: &[T], b: &[T], max_distance: f64) -> Option<usize> {
+    if max_distance < 0.0 || a.len().abs_diff(b.len()) as f64 > max_distance {
+        return None;
+    }
+
+    // No two strings are further apart than the longer one is long.
+    if max_distance >= a.len().max(b.len()) as f64 {
+        return Some(levenshtein_units(a, b));
+    }
+
+    // Only whole edits are counted, so any fractional part of the bound can
+    // be dropped. Cells are capped at `cutoff`, which stands for any distance
+    // larger than the bound.
+    let max_distance = max_distance as usize;
+    let cutoff = max_distance + 1;
+
+    let mut prev: Vec<usize> = (0..=b.len()).map(|j| j.min(cutoff)).collect();
+    let mut curr = vec![cutoff; b.len() + 1];
+
+    for (i, a_unit) in a.iter().enumerate() {
+        let row = i + 1;
+        let start = row.saturating_sub(max_distance).max(1);
+        let end = (row + max_distance).min(b.len());
+
+        // The cell left of the band is either the first column or outside
+        // the band.
+        curr[start - 1] = if start == 1 { row.min(cutoff) } else { cutoff };
+        let mut row_min = curr[start - 1];
+
+        for j in start..=end {
+            let substitution_cost = usize::from(*a_unit != b[j - 1]);
+
+            curr[j] = (prev[j - 1] + substitution_cost) // substitution
+                .min(prev[j] + 1) // deletion
+                .min(curr[j - 1] + 1) // insertion
+                .min(cutoff);
+            row_min = row_min.min(curr[j]);
+        }
+
+        // The next row's band reaches one column further, where this row is
+        // outside the band.
+        if end < b.len() {
+            curr[end + 1] = cutoff;
+        }
+
+        if row_min > max_distance {
+            return None;
+        }
+
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    let distance = prev[b.len()];
+    (distance <= max_distance).then_some(distance)
+}
+
+/// A unit of text that Myers' algorithm can look up in a pattern.
+trait PatternUnit: Eq + Hash {
+    /// Returns the unit's code if it is a single ASCII character, so that
+    /// its masks can be found in a table rather than a map.
+    fn ascii(&self) -> Option<usize>;
+}
+
+impl PatternUnit for u8 {
+    fn ascii(&self) -> Option<usize> {
+        self.is_ascii().then_some(usize::from(*self))
+    }
+}
+
+impl PatternUnit for char {
+    fn ascii(&self) -> Option<usize> {
+        self.is_ascii().then_some(*self as usize)
+    }
+}
+
+impl PatternUnit for &str {
+    fn ascii(&self) -> Option<usize> {
+        match self.as_bytes() {
+            [byte] if byte.is_ascii() => Some(usize::from(*byte)),
+            _ => None,
+        }
+    }
+}
+
+/// The positions at which each distinct unit occurs in a pattern, as bit
+/// masks of 64 positions per block.
+struct PatternMasks<'a, T> {
+    /// The masks of the ASCII character with code `c` are
+    /// `ascii[c * blocks..(c + 1) * blocks]`.
+    ascii: Vec<u64>,
+    /// The masks of every other unit.
+    others: HashMap<&'a T, Vec<u64>>,
+    blocks: usize,
+}
+
+impl<'a, T: PatternUnit> PatternMasks<'a, T> {
+    fn new(pattern: &'a [T]) -> Self {
+        let blocks = pattern.len().div_ceil(64);
+        let mut ascii = vec![0; 128 * blocks];
+        let mut others: HashMap<&T, Vec<u64>> = HashMap::new();
+
+        for (position, unit) in pattern.iter().enumerate() {
+            let masks = match unit.ascii() {
+                Some(code) => &mut ascii[code * blocks..(code + 1) * blocks],
+                None => others.entry(unit).or_insert_with(|| vec![0; blocks]),
+            };
+
+            masks[position / 64] |= 1 << (position % 64);
+        }
+
+        Self {
+            ascii,
+            others,
+            blocks,
+        }
+    }
+
+    /// Returns the masks of `unit`, or `None` if it is not in the pattern.
+    fn get(&self, unit: &T) -> Option<&[u64]> {
+        match unit.ascii() {
+            Some(code) => Some(&self.ascii[code * self.blocks..(code + 1) * self.blocks]),
+            None => self.others.get(unit).map(Vec::as_slice),
+        }
+    }
+}
+
+/// Advances one 64-row block of Myers' bit-vector algorithm by one column.
+///
+/// `vp` and `vn` mark the rows of the block where the vertical delta of the
+/// dynamic programming matrix is +1 and -1, `eq` marks the rows whose pattern
+/// unit equals the column's text unit, and `carry_in` is the horizontal delta
+/// entering the block's first row. Returns the horizontal delta leaving the
+/// row marked by `last_row`, following Hyyrö's formulation.
+fn advance_block(eq: u64, vp: &mut u64, vn: &mut u64, carry_in: i8, last_row: u64) -> i8 {
+    let carry_in_neg = u64::from(carry_in < 0);
+    let carry_in_pos = u64::from(carry_in > 0);
+
+    let xv = eq | *vn;
+    let eq = eq | carry_in_neg;
+    let xh = ((eq & *vp).wrapping_add(*vp) ^ *vp) | eq;
+    let hp = *vn | !(xh | *vp);
+    let hn = *vp & xh;
+
+    let carry_out = if hp & last_row != 0 {
+        1
+    } else if hn & last_row != 0 {
+        -1
+    } else {
+        0
+    };
+
+    let hp = (hp << 1) | carry_in_pos;
+    let hn = (hn << 1) | carry_in_neg;
+    *vp = hn | !(xv | hp);
+    *vn = hp & xv;
+
+    carry_out
+}
+
+/// Computes the Levenshtein distance with Myers' bit-vector algorithm, for
+/// patterns of 1 to 64 units.
+fn myers<T: PatternUnit>(pattern: &[T], text: &[T]) -> usize {
+    // The pattern fits in one block, so the masks of ASCII characters are
+    // kept in a table on the stack, indexed by character code. The map of
+    // other units only allocates if the pattern has any.
+    let mut ascii = [0u64; 128];
+    let mut others: HashMap<&T, u64> = HashMap::new();
+
+    for (position, unit) in pattern.iter().enumerate() {
+        match unit.ascii() {
+            Some(code) => ascii[code] |= 1 << position,
+            None => *others.entry(unit).or_default() |= 1 << position,
+        }
+    }
+
+    let last_row = 1 << (pattern.len() - 1);
+
+    // Every row of the first column is one more than the row above it.
+    let mut vp = !0;
+    let mut vn = 0;
+    let mut distance = pattern.len();
+
+    for unit in text {
+        let eq = match unit.ascii() {
+            Some(code) => ascii[code],
+            None => others.get(unit).copied().unwrap_or(0),
+        };
+
+        // Every column of the first row is one more than the column before it.
+        match advance_block(eq, &mut vp, &mut vn, 1, last_row) {
+            1 => distance += 1,
+            -1 => distance -= 1,
+            _ => {}
+        }
+    }
+
+    distance
+}
+
+/// Computes the Levenshtein distance with the block-based variant of Myers'
+/// algorithm, which splits patterns longer than 64 units into 64-row blocks
+/// and carries horizontal deltas from each block into the next.
+fn myers_blocks<T: PatternUnit>(pattern: &[T], text: &[T]) -> usize {
+    let masks = PatternMasks::new(pattern);
+    let blocks = masks.blocks;
+    let last_row = 1 << ((pattern.len() - 1) % 64);
+
+    let mut vp = vec![!0; blocks];
+    let mut vn = vec![0; blocks];
+    let mut distance = pattern.len();
+
+    for unit in text {
+        let eq = masks.get(unit);
+        let mut carry = 1;
+
+        for block in 0..blocks {
+            let block_last_row = if block + 1 == blocks {
+                last_row
+            } else {
+                1 << 63
+            };
+            carry = advance_block(
+                eq.map_or(0, |masks| masks[block]),
+                &mut vp[block],
+                &mut vn[block],
+                carry,
+                block_last_row,
+            );
+        }
+
+        match carry {
+            1 => distance += 1,
+            -1 => distance -= 1,
+            _ => {}
+        }
+    }
+
+    distance
+}
+
+/// Computes the optimal string alignment distance between two strings.
+///
+/// This is the restricted Damerau-Levenshtein distance: in addition to
+/// insertions, deletions and substitutions, swapping two adjacent characters
+/// counts as a single edit. No substring may be edited more than once, so
+/// for example "ca" to "abc" costs 3 rather than 2.
+pub fn osa_distance(a: &str, b: &str) -> usize {
+    with_units!(Unit::Chars, a, b, |a, b| osa_units(a, b))
+}
+
+fn osa_units<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    // A transposition looks two rows back, so three rows are kept.
+    let mut prev_prev = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 0..a.len() {
+        curr[0] = i + 1;
+
+        for j in 0..b.len() {
+            let substitution_cost = usize::from(a[i] != b[j]);
+
+            curr[j + 1] = (prev[j] + substitution_cost) // substitution
+                .min(prev[j + 1] + 1) // deletion
+                .min(curr[j] + 1); // insertion
+
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                curr[j + 1] = curr[j + 1].min(prev_prev[j - 1] + 1); // transposition
+            }
+        }
+
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Computes the unrestricted Damerau-Levenshtein distance between two strings.
+///
+/// Insertions, deletions, substitutions and transpositions of two adjacent
+/// characters each cost one edit, and characters may be edited again after
+/// being transposed. Unlike [`osa_distance`] this is a true metric.
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    with_units!(Unit::Chars, a, b, |a, b| damerau_levenshtein_units(a, b))
+}
+
+fn damerau_levenshtein_units<T: Eq + Hash>(a: &[T], b: &[T]) -> usize {
+    // The matrix has an extra leading row and column holding a "maximum"
+    // distance, which stops transpositions from reaching before the start
+    // of either string.
+    let max_distance = a.len() + b.len();
+    let width = b.len() + 2;
+    let mut matrix = vec![0; (a.len() + 2) * width];
+    let at = |i: usize, j: usize| i * width + j;
+
+    matrix[at(0, 0)] = max_distance;
+    for i in 0..=a.len() {
+        matrix[at(i + 1, 0)] = max_distance;
+        matrix[at(i + 1, 1)] = i;
+    }
+    for j in 0..=b.len() {
+        matrix[at(0, j + 1)] = max_distance;
+        matrix[at(1, j + 1)] = j;
+    }
+
+    // The last row in which each unit of `a` was seen.
+    let mut last_row = HashMap::new();
+
+    for i in 1..=a.len() {
+        // The last column in the current row where the units matched.
+        let mut last_match_col = 0;
+
+        for j in 1..=b.len() {
+            let last_match_row = last_row.get(&b[j - 1]).copied().unwrap_or(0);
+            let last_match_col_before = last_match_col;
+            let substitution_cost = if a[i - 1] == b[j - 1] {
+                last_match_col = j;
+                0
+            } else {
+                1
+            };
+
+            matrix[at(i + 1, j + 1)] = (matrix[at(i, j)] + substitution_cost) // substitution
+                .min(matrix[at(i + 1, j)] + 1) // insertion
+                .min(matrix[at(i, j + 1)] + 1) // deletion
+                .min(
+                    // transposition, with any units in between deleted or inserted
+                    matrix[at(last_match_row, last_match_col_before)]
+                        + (i - last_match_row - 1)
+                        + 1
+                        + (j - last_match_col_before - 1),
+                );
+        }
+
+        last_row.insert(&a[i - 1], i);
+    }
+
+    matrix[at(a.len() + 1, b.len() + 1)]
+}
+
+/// Computes the Jaro similarity between two strings.
+///
+/// The similarity ranges from 0 (no characters in common) to 1 (identical),
+/// and is based on the number of matching characters and transpositions
+/// between them. Characters only match if they are no further apart than
+/// half the length of the longer string.
+pub fn jaro(a: &str, b: &str) -> f64 {
+    with_units!(Unit::Chars, a, b, |a, b| jaro_units(a, b))
+}
+
+fn jaro_units<T: PartialEq>(a: &[T], b: &[T]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let match_window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+
+    for (i, a_unit) in a.iter().enumerate() {
+        let start = i.saturating_sub(match_window);
+        let end = (i + match_window + 1).min(b.len());
+
+        for j in start..end {
+            if !b_matched[j] && b[j] == *a_unit {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    // Count the matched units that appear in a different order. Each
+    // transposition is counted twice, once from each side.
+    let a_matches = a.iter().zip(&a_matched).filter(|(_, m)| **m);
+    let b_matches = b.iter().zip(&b_matched).filter(|(_, m)| **m);
+    let half_transpositions = a_matches
+        .zip(b_matches)
+        .filter(|((a_unit, _), (b_unit, _))| a_unit != b_unit)
+        .count();
+
+    let matches = matches as f64;
+    let transpositions = (half_transpositions / 2) as f64;
+
+    (matches / a.len() as f64 + matches / b.len() as f64 + (matches - transpositions) / matches)
+        / 3.0
+}
+
+/// Computes the Jaro-Winkler similarity between two strings using the
+/// standard parameters of [`JaroWinkler::default`].
+///
+/// Jaro-Winkler boosts the [`jaro`] similarity of strings that share a
+/// common prefix, which suits short strings such as personal names.
+pub fn jaro_winkler(a: &str, b: &str) -> f64 {
+    JaroWinkler::default().similarity(a, b)
+}
+
+/// Computes the Hamming distance between two strings.
+///
+/// The distance is the number of positions at which the characters differ.
+/// Hamming distance is only defined for strings of equal length, so `None`
+/// is returned when the lengths (in characters) differ.
+pub fn hamming(a: &str, b: &str) -> Option<usize> {
+    with_units!(Unit::Chars, a, b, |a, b| hamming_units(a, b))
+}
+
+fn hamming_units<T: PartialEq>(a: &[T], b: &[T]) -> Option<usize> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    Some(
+        a.iter()
+            .zip(b)
+            .filter(|(a_unit, b_unit)| a_unit != b_unit)
+            .count(),
+    )
+}
+
+fn positional_units<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    // Case 0: a is longer than b, so add nonzero distance using abs_diff.
+    // Case 1: b is longer than a, so add nonzero distance using abs_diff.
+    // Case 2: a and b are the same length, so add zero distance
+    let mut distance = a.len().abs_diff(b.len());
+
+    for (a_unit, b_unit) in a.iter().zip(b) {
+        // If the units are not equal, add one to the distance.
+        if a_unit != b_unit {
+            distance += 1;
+        }
+    }
+
+    distance
+}
+
+/// Levenshtein edit distance: insertions, deletions and substitutions each
+/// cost one edit. See [`levenshtein`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Levenshtein {
+    unit: Unit,
+}
+
+impl Levenshtein {
+    /// Creates a Levenshtein metric that compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Metric for Levenshtein {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| levenshtein_units(a, b) as f64)
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            normalized_similarity(levenshtein_units(a, b) as f64, a.len(), b.len())
+        })
+    }
+
+    fn similarity_from_distance(&self, a: &str, b: &str, distance: f64) -> f64 {
+        self.unit.similarity_from_distance(a, b, distance)
+    }
+
+    fn distance_within(&self, a: &str, b: &str, max_distance: f64) -> Option<f64> {
+        // Check the lengths before splitting the strings into units, which
+        // is most of the cost of rejecting a string.
+        if self.unit == Unit::Chars
+            && a.chars().count().abs_diff(b.chars().count()) as f64 > max_distance
+        {
+            return None;
+        }
+
+        with_units!(self.unit, a, b, |a, b| {
+            levenshtein_within_units(a, b, max_distance).map(|distance| distance as f64)
+        })
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+
+    fn bounded_by_deletions(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+
+    fn bounded_by_levenshtein(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+}
+
+/// Optimal string alignment (restricted Damerau-Levenshtein) distance:
+/// adjacent transpositions cost one edit. See [`osa_distance`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptimalStringAlignment {
+    unit: Unit,
+}
+
+impl OptimalStringAlignment {
+    /// Creates an optimal string alignment metric that compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Metric for OptimalStringAlignment {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| osa_units(a, b) as f64)
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            normalized_similarity(osa_units(a, b) as f64, a.len(), b.len())
+        })
+    }
+
+    fn similarity_from_distance(&self, a: &str, b: &str, distance: f64) -> f64 {
+        self.unit.similarity_from_distance(a, b, distance)
+    }
+
+    fn bounded_by_deletions(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+}
+
+/// Unrestricted Damerau-Levenshtein distance: adjacent transpositions cost
+/// one edit. See [`damerau_levenshtein`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DamerauLevenshtein {
+    unit: Unit,
+}
+
+impl DamerauLevenshtein {
+    /// Creates a Damerau-Levenshtein metric that compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Metric for DamerauLevenshtein {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| damerau_levenshtein_units(a, b)
+            as f64)
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            normalized_similarity(damerau_levenshtein_units(a, b) as f64, a.len(), b.len())
+        })
+    }
+
+    fn similarity_from_distance(&self, a: &str, b: &str, distance: f64) -> f64 {
+        self.unit.similarity_from_distance(a, b, distance)
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+
+    fn bounded_by_deletions(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+}
+
+/// Jaro similarity. The distance is `1 - similarity`. See [`jaro`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Jaro {
+    unit: Unit,
+}
+
+impl Jaro {
+    /// Creates a Jaro metric that compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Metric for Jaro {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        1.0 - self.similarity(a, b)
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| jaro_units(a, b))
+    }
+}
+
+/// Jaro-Winkler similarity. The distance is `1 - similarity`.
+///
+/// Strings whose [`jaro`] similarity exceeds the boost threshold have it
+/// raised in proportion to the length of their common prefix (up to four
+/// units) multiplied by the prefix scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JaroWinkler {
+    prefix_scale: f64,
+    boost_threshold: f64,
+    unit: Unit,
+}
+
+impl JaroWinkler {
+    /// The longest common prefix that contributes to the boost.
+    const MAX_PREFIX_LEN: usize = 4;
+
+    /// Creates a Jaro-Winkler metric with the standard prefix scale of 0.1 and
+    /// boost threshold of 0.7, which compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self {
+            prefix_scale: 0.1,
+            boost_threshold: 0.7,
+            unit: Unit::Chars,
+        }
+    }
+
+    /// Sets how much each character of common prefix boosts the similarity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_scale` is not between 0 and 0.25, since larger
+    /// values can produce similarities above 1.
+    pub fn with_prefix_scale(mut self, prefix_scale: f64) -> Self {
+        assert!(
+            (0.0..=0.25).contains(&prefix_scale),
+            "prefix scale must be between 0 and 0.25"
+        );
+        self.prefix_scale = prefix_scale;
+        self
+    }
+
+    /// Sets the Jaro similarity above which the prefix boost is applied.
+    pub fn with_boost_threshold(mut self, boost_threshold: f64) -> Self {
+        self.boost_threshold = boost_threshold;
+        self
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Default for JaroWinkler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metric for JaroWinkler {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        1.0 - self.similarity(a, b)
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            let similarity = jaro_units(a, b);
+            if similarity <= self.boost_threshold {
+                return similarity;
+            }
+
+            let prefix_len = a
+                .iter()
+                .zip(b)
+                .take(Self::MAX_PREFIX_LEN)
+                .take_while(|(a_unit, b_unit)| a_unit == b_unit)
+                .count();
+
+            similarity + prefix_len as f64 * self.prefix_scale * (1.0 - similarity)
+        })
+    }
+}
+
+/// Hamming distance: the number of differing positions. Strings of
+/// different lengths are infinitely far apart. See [`hamming`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hamming {
+    unit: Unit,
+}
+
+impl Hamming {
+    /// Creates a Hamming metric that compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Metric for Hamming {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            hamming_units(a, b).map_or(f64::INFINITY, |distance| distance as f64)
+        })
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            hamming_units(a, b).map_or(0.0, |distance| {
+                normalized_similarity(distance as f64, a.len(), b.len())
+            })
+        })
+    }
+
+    fn similarity_from_distance(&self, a: &str, b: &str, distance: f64) -> f64 {
+        self.unit.similarity_from_distance(a, b, distance)
+    }
+
+    fn bounded_by_deletions(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+
+    fn bounded_by_levenshtein(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+}
+
+/// Positional metric: the number of mismatched units when the strings are
+/// compared position by position, plus the difference in their lengths.
+///
+/// This was the metric originally used by `FuzzySearcher`. Unlike
+/// [`Levenshtein`] it does not realign the strings after an insertion or
+/// deletion, so a single dropped letter can make every following unit
+/// mismatch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Positional {
+    unit: Unit,
+}
+
+impl Positional {
+    /// Creates a positional metric that compares [`Unit::Chars`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit of text that is compared and counted.
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl Metric for Positional {
+    fn distance(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| positional_units(a, b) as f64)
+    }
+
+    fn similarity(&self, a: &str, b: &str) -> f64 {
+        with_units!(self.unit, a, b, |a, b| {
+            normalized_similarity(positional_units(a, b) as f64, a.len(), b.len())
+        })
+    }
+
+    fn similarity_from_distance(&self, a: &str, b: &str, distance: f64) -> f64 {
+        self.unit.similarity_from_distance(a, b, distance)
+    }
+
+    fn bounded_by_deletions(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+
+    fn bounded_by_levenshtein(&self) -> bool {
+        self.unit == Unit::Chars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck_macros::quickcheck;
+
+    use super::*;
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+    }
+
+    /// The textbook dynamic programming algorithm that Myers' algorithm is
+    /// checked against.
+    fn levenshtein_dp<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+        let mut prev: Vec<usize> = (0..=b.len()).collect();
+        let mut curr = vec![0; b.len() + 1];
+
+        for (i, a_unit) in a.iter().enumerate() {
+            curr[0] = i + 1;
+
+            for (j, b_unit) in b.iter().enumerate() {
+                let substitution_cost = usize::from(a_unit != b_unit);
+                curr[j + 1] = (prev[j] + substitution_cost)
+                    .min(prev[j + 1] + 1)
+                    .min(curr[j] + 1);
+            }
+
+            std::mem::swap(&mut prev, &mut curr);
+        }
+
+        prev[b.len()]
+    }
+
+    /// Maps arbitrary bytes onto a small alphabet, so that generated strings
+    /// share enough units to exercise matches as well as edits.
+    fn small_alphabet(bytes: &[u8]) -> Vec<char> {
+        bytes
+            .iter()
+            .map(|&byte| char::from(b'a' + byte % 4))
+            .collect()
+    }
+
+    #[quickcheck]
+    fn myers_agrees_with_dynamic_programming(a: Vec<u8>, b: Vec<u8>) -> bool {
+        let (a, b) = (small_alphabet(&a), small_alphabet(&b));
+        levenshtein_units(&a, &b) == levenshtein_dp(&a, &b)
+    }
+
+    #[quickcheck]
+    fn myers_agrees_with_dynamic_programming_on_unicode(a: String, b: String) -> bool {
+        let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+        levenshtein_units(&a, &b) == levenshtein_dp(&a, &b)
+    }
+
+    #[quickcheck]
+    fn block_myers_agrees_with_dynamic_programming(
+        pattern: Vec<u8>,
+        len: u8,
+        edits: Vec<(u16, u8)>,
+    ) -> bool {
+        // Patterns of 65 to 320 units span several blocks, and the text is an
+        // edited copy of the pattern so the distance stays interesting.
+        let len = 65 + usize::from(len);
+        let pattern: Vec<char> = (0..len)
+            .map(|i| {
+                pattern
+                    .get(i % pattern.len().max(1))
+                    .map_or(i as u8, |&byte| byte ^ i as u8)
+            })
+            .map(|byte| char::from(b'a' + byte % 4))
+            .collect();
+        let mut text = pattern.clone();
+
+        for (position, byte) in edits {
+            let position = usize::from(position) % (text.len() + 1);
+            match byte % 3 {
+                0 => text.insert(position, char::from(b'a' + byte % 5)),
+                1 if position < text.len() => {
+                    text.remove(position);
+                }
+                _ if position < text.len() => text[position] = char::from(b'a' + byte % 5),
+                _ => {}
+            }
+        }
+
+        levenshtein_units(&pattern, &text) == levenshtein_dp(&pattern, &text)
+            && levenshtein_units(&text, &pattern) == levenshtein_dp(&pattern, &text)
+    }
+
+    #[test]
+    fn myers_handles_block_boundaries() {
+        for len in [63, 64, 65, 127, 128, 129] {
+            let a: Vec<char> = "abcd".chars().cycle().take(len).collect();
+            let b: Vec<char> = "abdc".chars().cycle().take(len + 3).collect();
+
+            assert_eq!(levenshtein_units(&a, &a), 0);
+            assert_eq!(levenshtein_units(&a, &[]), len);
+            assert_eq!(levenshtein_units(&a, &b), levenshtein_dp(&a, &b));
+            assert_eq!(levenshtein_units(&b, &a), levenshtein_dp(&a, &b));
+        }
+    }
+
+    #[quickcheck]
+    fn bounded_levenshtein_agrees_with_dynamic_programming(
+        a: Vec<u8>,
+        b: Vec<u8>,
+        max_distance: u8,
+    ) -> bool {
+        let (a, b) = (small_alphabet(&a), small_alphabet(&b));
+        let distance = levenshtein_dp(&a, &b);
+
+        // Also check fractional bounds, which only allow whole edits.
+        [
+            f64::from(max_distance % 16),
+            f64::from(max_distance % 16) + 0.5,
+        ]
+        .into_iter()
+        .all(|max_distance| {
+            levenshtein_within_units(&a, &b, max_distance)
+                == (distance as f64 <= max_distance).then_some(distance)
+        })
+    }
+
+    #[test]
+    fn bounded_levenshtein_rejects_distant_strings() {
+        let metric = Levenshtein::new();
+        assert_eq!(metric.distance_within("kitten", "sitting", 3.0), Some(3.0));
+        assert_eq!(metric.distance_within("kitten", "sitting", 2.0), None);
+        assert_eq!(metric.distance_within("a", "abcdef", 4.0), None);
+        assert_eq!(metric.distance_within("abc", "abc", 0.0), Some(0.0));
+        assert_eq!(metric.distance_within("abc", "abc", -1.0), None);
+        assert_eq!(Jaro::new().distance_within("abc", "xyz", 0.5), None);
+    }
+
+    #[test]
+    fn levenshtein_scores_dropped_letters_as_one_edit() {
+        assert_eq!(levenshtein("helo", "hello"), 1);
+        assert_eq!(levenshtein("hello", "helo"), 1);
+        assert_eq!(levenshtein("hhello", "hello"), 1);
+    }
+
+    #[test]
+    fn transpositions_cost_one_edit() {
+        assert_eq!(levenshtein("teh", "the"), 2);
+        assert_eq!(osa_distance("teh", "the"), 1);
+        assert_eq!(damerau_levenshtein("teh", "the"), 1);
+        assert_eq!(osa_distance("abcdef", "abcdfe"), 1);
+        assert_eq!(damerau_levenshtein("abcdef", "abcdfe"), 1);
+    }
+
+    #[test]
+    fn damerau_levenshtein_counts_other_edits() {
+        for (a, b, expected) in [
+            ("", "", 0),
+            ("abc", "", 3),
+            ("", "abc", 3),
+            ("kitten", "sitting", 3),
+            ("helo", "hello", 1),
+        ] {
+            assert_eq!(osa_distance(a, b), expected);
+            assert_eq!(damerau_levenshtein(a, b), expected);
+        }
+    }
+
+    #[test]
+    fn osa_does_not_edit_substrings_twice() {
+        assert_eq!(osa_distance("ca", "abc"), 3);
+        assert_eq!(damerau_levenshtein("ca", "abc"), 2);
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn jaro_matches_reference_values() {
+        assert_close(jaro("", ""), 1.0);
+        assert_close(jaro("abc", ""), 0.0);
+        assert_close(jaro("abc", "xyz"), 0.0);
+        assert_close(jaro("martha", "marhta"), 0.944);
+        assert_close(jaro("dixon", "dicksonx"), 0.767);
+        assert_close(jaro("jellyfish", "smellyfish"), 0.896);
+    }
+
+    #[test]
+    fn jaro_winkler_boosts_common_prefixes() {
+        assert_close(jaro_winkler("martha", "marhta"), 0.961);
+        assert_close(jaro_winkler("dixon", "dicksonx"), 0.813);
+        assert_close(jaro_winkler("dwayne", "duane"), 0.840);
+    }
+
+    #[test]
+    fn jaro_winkler_is_configurable() {
+        let no_boost = JaroWinkler::new().with_prefix_scale(0.0);
+        assert_close(
+            no_boost.similarity("martha", "marhta"),
+            jaro("martha", "marhta"),
+        );
+
+        let high_threshold = JaroWinkler::new().with_boost_threshold(0.95);
+        assert_close(
+            high_threshold.similarity("martha", "marhta"),
+            jaro("martha", "marhta"),
+        );
+
+        assert_close(JaroWinkler::new().distance("martha", "marhta"), 1.0 - 0.961);
+    }
+
+    #[test]
+    fn hamming_requires_equal_lengths() {
+        assert_eq!(hamming("karolin", "kathrin"), Some(3));
+        assert_eq!(hamming("abc", "abcd"), None);
+        assert_eq!(Hamming::new().similarity("abc", "abcd"), 0.0);
+    }
+
+    #[test]
+    fn positional_does_not_realign() {
+        assert_eq!(Positional::new().distance("helo", "hello"), 2.0);
+        assert_eq!(Levenshtein::new().distance("helo", "hello"), 1.0);
+    }
+
+    #[test]
+    fn accented_letters_are_one_edit_in_any_encoding() {
+        let nfc = "caf\u{e9}";
+        let nfd = "cafe\u{301}";
+
+        for unit in [Unit::Chars, Unit::Graphemes] {
+            let metric = Levenshtein::new().with_unit(unit);
+            assert_eq!(metric.distance(nfc, "cafe"), 1.0);
+            assert_eq!(metric.distance(nfd, "cafe"), 1.0);
+        }
+
+        // Only grapheme clusters see the two encodings as a single unit.
+        assert_eq!(Levenshtein::new().distance(nfc, nfd), 2.0);
+        let graphemes = Levenshtein::new().with_unit(Unit::Graphemes);
+        assert_eq!(graphemes.distance(nfc, nfd), 1.0);
+        assert_eq!(graphemes.similarity(nfd, "cafe"), 0.75);
+    }
+
+    #[test]
+    fn lengths_are_counted_in_units_not_bytes() {
+        assert_eq!(
+            Levenshtein::new().similarity("\u{6771}\u{4eac}", "\u{6771}\u{90fd}"),
+            0.5
+        );
+        assert_eq!(Positional::new().distance("caf\u{e9}", "cafe"), 1.0);
+        assert_eq!(Hamming::new().distance("caf\u{e9}", "cafe"), 1.0);
+
+        let graphemes = Positional::new().with_unit(Unit::Graphemes);
+        assert_eq!(graphemes.distance("cafe\u{301}", "cafe"), 1.0);
+        assert_eq!(
+            DamerauLevenshtein::new()
+                .with_unit(Unit::Graphemes)
+                .distance("e\u{301}a", "ae\u{301}"),
+            1.0
+        );
+        assert_eq!(
+            Jaro::new()
+                .with_unit(Unit::Graphemes)
+                .similarity("e\u{301}", "e\u{301}"),
+            1.0
+        );
+    }
+
+    #[test]
+    fn similarities_can_be_derived_from_distances() {
+        let metrics: [&dyn Metric; 6] = [
+            &Levenshtein::new(),
+            &Levenshtein::new().with_unit(Unit::Graphemes),
+            &OptimalStringAlignment::new(),
+            &DamerauLevenshtein::new(),
+            &Hamming::new(),
+            &Positional::new(),
+        ];
+        let pairs = [
+            ("", ""),
+            ("helo", "hello"),
+            ("abc", "acb"),
+            ("kitten", "sitting"),
+            ("cafe\u{301}", "caf\u{e9}s"),
+            ("abcd", "xbcz"),
+        ];
+
+        for metric in metrics {
+            for (a, b) in pairs {
+                let distance = metric.distance(a, b);
+                assert_eq!(
+                    metric.similarity_from_distance(a, b, distance),
+                    metric.similarity(a, b)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn similarity_is_normalized() {
+        assert_eq!(Levenshtein::new().similarity("", ""), 1.0);
+        assert_eq!(Levenshtein::new().similarity("abc", "abc"), 1.0);
+        assert_eq!(Levenshtein::new().similarity("abc", "xyz"), 0.0);
+        assert_eq!(Levenshtein::new().similarity("helo", "hello"), 0.8);
+    }
+}
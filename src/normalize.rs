@@ -0,0 +1,362 @@
+//! Text normalization applied to both the corpus and queries.
+//!
+//! A [`Normalizer`] is a pipeline of independently toggleable steps. Corpus
+//! strings are normalized once when the normalizer is configured on a
+//! [`FuzzySearcher`](crate::FuzzySearcher), and each query is normalized
+//! before it is compared. Search results still return the original corpus
+//! text.
+
+use std::borrow::Cow;
+
+use caseless::Caseless;
+use unicode_categories::UnicodeCategories;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark, is_nfc, is_nfkc};
+
+/// A Unicode normalization form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnicodeForm {
+    /// Canonical composition, so that precomposed and decomposed characters
+    /// compare equal.
+    Nfc,
+    /// Compatibility composition, which additionally folds compatibility
+    /// characters such as ligatures and full-width forms.
+    Nfkc,
+}
+
+/// A configurable text normalization pipeline.
+///
+/// Every step is disabled by default, so `Normalizer::new()` leaves text
+/// unchanged. Enabled steps run in this order:
+///
+/// 1. Unicode normalization to the configured [`UnicodeForm`].
+/// 2. Diacritic stripping, which removes combining marks.
+/// 3. Full case folding, as defined by the Unicode `CaseFolding.txt` table.
+/// 4. Punctuation removal.
+/// 5. Whitespace collapsing, which trims the text and replaces each run of
+///    whitespace with a single space.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Normalizer {
+    unicode_form: Option<UnicodeForm>,
+    case_fold: bool,
+    strip_diacritics: bool,
+    collapse_whitespace: bool,
+    remove_punctuation: bool,
+}
+
+impl Normalizer {
+    /// Creates a normalizer with every step disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the Unicode normalization form, or disables Unicode
+    /// normalization if `None`.
+    pub fn with_unicode_form(mut self, unicode_form: Option<UnicodeForm>) -> Self {
+        self.unicode_form = unicode_form;
+        self
+    }
+
+    /// Enables or disables full case folding, so that strings differing only
+    /// in case compare equal.
+    pub fn with_case_fold(mut self, case_fold: bool) -> Self {
+        self.case_fold = case_fold;
+        self
+    }
+
+    /// Enables or disables diacritic stripping, so that "café" compares
+    /// equal to "cafe".
+    pub fn with_strip_diacritics(mut self, strip_diacritics: bool) -> Self {
+        self.strip_diacritics = strip_diacritics;
+        self
+    }
+
+    /// Enables or disables whitespace collapsing.
+    pub fn with_collapse_whitespace(mut self, collapse_whitespace: bool) -> Self {
+        self.collapse_whitespace = collapse_whitespace;
+        self
+    }
+
+    /// Enables or disables punctuation removal.
+    pub fn with_remove_punctuation(mut self, remove_punctuation: bool) -> Self {
+        self.remove_punctuation = remove_punctuation;
+        self
+    }
+
+    /// Returns `true` if every step is disabled, so that normalizing text
+    /// leaves it unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Normalizes `text` by running it through each enabled step.
+    ///
+    /// Text is only copied if an enabled step needs to change it.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.is_identity() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut text = Cow::Borrowed(text);
+
+        if let Some(form) = self.unicode_form {
+            apply(
+                &mut text,
+                |text| is_composed(text, form),
+                |text| compose(text, form),
+            );
+        }
+
+        if self.strip_diacritics {
+            apply(
+                &mut text,
+                |text| is_nfc(text) && !text.nfd().any(is_combining_mark),
+                |text| {
+                    let stripped: String = text.nfd().filter(|&c| !is_combining_mark(c)).collect();
+                    stripped.nfc().collect()
+                },
+            );
+        }
+
+        if self.case_fold {
+            apply(
+                &mut text,
+                |text| text.chars().default_case_fold().eq(text.chars()),
+                |text| text.chars().default_case_fold().collect(),
+            );
+
+            // Folding can produce decomposed characters, such as the dotted
+            // capital I, so recompose them into the requested form.
+            if let Some(form) = self.unicode_form {
+                apply(
+                    &mut text,
+                    |text| is_composed(text, form),
+                    |text| compose(text, form),
+                );
+            }
+        }
+
+        if self.remove_punctuation {
+            apply(
+                &mut text,
+                |text| !text.chars().any(|c| c.is_punctuation()),
+                |text| text.chars().filter(|c| !c.is_punctuation()).collect(),
+            );
+        }
+
+        if self.collapse_whitespace {
+            apply(&mut text, is_collapsed, |text| {
+                text.split_whitespace().collect::<Vec<_>>().join(" ")
+            });
+        }
+
+        text
+    }
+}
+
+/// The casing pattern of a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Casing {
+    /// Every cased letter is lowercase.
+    Lower,
+    /// Every cased letter is uppercase, and there is more than one.
+    Upper,
+    /// The first cased letter is uppercase and the rest are lowercase.
+    Title,
+    /// Any other pattern, or no cased letters at all.
+    Mixed,
+}
+
+impl Casing {
+    fn of(text: &str) -> Self {
+        let mut cased = text
+            .chars()
+            .filter(|c| c.is_lowercase() || c.is_uppercase());
+
+        let Some(first) = cased.next() else {
+            return Casing::Mixed;
+        };
+        let (mut any_lower, mut any_upper) = (false, false);
+        for c in cased {
+            any_lower |= c.is_lowercase();
+            any_upper |= c.is_uppercase();
+        }
+
+        match (first.is_uppercase(), any_lower, any_upper) {
+            (false, _, false) => Casing::Lower,
+            (true, false, true) => Casing::Upper,
+            (true, _, false) => Casing::Title,
+            _ => Casing::Mixed,
+        }
+    }
+}
+
+/// Applies the casing pattern of `pattern` to `text`.
+///
+/// Lowercase, uppercase and title-case patterns are copied, so that with a
+/// pattern of "Helo" the text "hello" becomes "Hello", and with "HELO" it
+/// becomes "HELLO". Text is returned unchanged if the pattern mixes cases in
+/// any other way, or has no cased letters.
+pub fn match_case(pattern: &str, text: &str) -> String {
+    match Casing::of(pattern) {
+        Casing::Lower => text.to_lowercase(),
+        Casing::Upper => text.to_uppercase(),
+        Casing::Title => {
+            let mut chars = text.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.as_str().to_lowercase().chars())
+                    .collect(),
+                None => String::new(),
+            }
+        }
+        Casing::Mixed => text.to_string(),
+    }
+}
+
+/// Normalizes `text` to the given Unicode form.
+fn compose(text: &str, form: UnicodeForm) -> String {
+    match form {
+        UnicodeForm::Nfc => text.nfc().collect(),
+        UnicodeForm::Nfkc => text.nfkc().collect(),
+    }
+}
+
+/// Returns `true` if `text` is already in the given Unicode form.
+fn is_composed(text: &str, form: UnicodeForm) -> bool {
+    match form {
+        UnicodeForm::Nfc => is_nfc(text),
+        UnicodeForm::Nfkc => is_nfkc(text),
+    }
+}
+
+/// Returns `true` if `text` has no leading or trailing whitespace, and every
+/// other run of whitespace is a single space.
+fn is_collapsed(text: &str) -> bool {
+    let mut after_space = true;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if after_space || c != ' ' {
+                return false;
+            }
+            after_space = true;
+        } else {
+            after_space = false;
+        }
+    }
+
+    text.is_empty() || !after_space
+}
+
+/// Runs a normalization step on `text`, unless `unchanged` shows that the
+/// step would leave it as it is, so that text is only copied when needed.
+fn apply(
+    text: &mut Cow<'_, str>,
+    unchanged: impl FnOnce(&str) -> bool,
+    step: impl FnOnce(&str) -> String,
+) {
+    if !unchanged(text) {
+        *text = Cow::Owned(step(text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_normalizer_is_identity() {
+        let normalizer = Normalizer::new();
+        assert!(normalizer.is_identity());
+        assert!(matches!(
+            normalizer.normalize("Café, Bar!"),
+            Cow::Borrowed("Café, Bar!")
+        ));
+    }
+
+    #[test]
+    fn unicode_forms_unify_encodings() {
+        let nfc = Normalizer::new().with_unicode_form(Some(UnicodeForm::Nfc));
+        assert_eq!(nfc.normalize("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(nfc.normalize("\u{fb01}le"), "\u{fb01}le");
+
+        let nfkc = Normalizer::new().with_unicode_form(Some(UnicodeForm::Nfkc));
+        assert_eq!(nfkc.normalize("\u{fb01}le"), "file");
+    }
+
+    #[test]
+    fn case_folding_handles_expansions() {
+        let normalizer = Normalizer::new().with_case_fold(true);
+        assert_eq!(normalizer.normalize("Hello WORLD"), "hello world");
+        assert_eq!(normalizer.normalize("Stra\u{df}e"), "strasse");
+        assert_eq!(normalizer.normalize("STRASSE"), "strasse");
+    }
+
+    #[test]
+    fn case_folding_covers_the_full_unicode_table() {
+        let normalizer = Normalizer::new().with_case_fold(true);
+        assert_eq!(normalizer.normalize("\u{17f}tar"), "star");
+        assert_eq!(normalizer.normalize("\u{3d0}\u{3b1}"), "\u{3b2}\u{3b1}");
+        assert_eq!(normalizer.normalize("\u{345}"), "\u{3b9}");
+        assert_eq!(normalizer.normalize("\u{130}"), "i\u{307}");
+        assert_eq!(normalizer.normalize("\u{fb03}"), "ffi");
+    }
+
+    #[test]
+    fn text_is_only_copied_when_a_step_changes_it() {
+        let normalizer = Normalizer::new()
+            .with_unicode_form(Some(UnicodeForm::Nfkc))
+            .with_strip_diacritics(true)
+            .with_case_fold(true)
+            .with_remove_punctuation(true)
+            .with_collapse_whitespace(true);
+
+        assert!(matches!(
+            normalizer.normalize("hello world"),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(normalizer.normalize(""), Cow::Borrowed(_)));
+        for changed in [
+            "Hello",
+            "caf\u{e9}",
+            "hello, world",
+            "hello  world",
+            " hello",
+        ] {
+            assert!(matches!(normalizer.normalize(changed), Cow::Owned(_)));
+        }
+    }
+
+    #[test]
+    fn diacritics_are_stripped_in_any_encoding() {
+        let normalizer = Normalizer::new().with_strip_diacritics(true);
+        assert_eq!(normalizer.normalize("caf\u{e9}"), "cafe");
+        assert_eq!(normalizer.normalize("cafe\u{301}"), "cafe");
+        assert_eq!(
+            normalizer.normalize("na\u{ef}ve \u{c5}ngstr\u{f6}m"),
+            "naive Angstrom"
+        );
+    }
+
+    #[test]
+    fn casing_is_copied_from_the_pattern() {
+        assert_eq!(match_case("Helo", "hello"), "Hello");
+        assert_eq!(match_case("HELO", "hello"), "HELLO");
+        assert_eq!(match_case("helo", "Hello"), "hello");
+        assert_eq!(match_case("I", "a"), "A");
+        assert_eq!(match_case("hElO", "Hello"), "Hello");
+        assert_eq!(match_case("123", "Hello"), "Hello");
+        assert_eq!(match_case("\u{c9}COLE", "\u{e9}cole"), "\u{c9}COLE");
+    }
+
+    #[test]
+    fn punctuation_and_whitespace_are_cleaned_up() {
+        let normalizer = Normalizer::new()
+            .with_remove_punctuation(true)
+            .with_collapse_whitespace(true);
+        assert_eq!(
+            normalizer.normalize("  Hello,\t\u{201c}world\u{201d}!\n "),
+            "Hello world"
+        );
+    }
+}
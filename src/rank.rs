@@ -0,0 +1,149 @@
+//! Ranking of matches by both distance and corpus weight.
+//!
+//! A weighted [`Corpus`](crate::Corpus) records how likely each string is to
+//! be intended before any query is seen, such as how often a word occurs in
+//! English text. A [`Blend`] combines a match's distance with that weight, so
+//! that a [`FuzzySearcher`](crate::FuzzySearcher) configured with
+//! [`FuzzySearcher::with_ranking`](crate::FuzzySearcher::with_ranking) prefers
+//! "the" to "thy" when correcting "thw", even though both are one edit away.
+
+/// A way of combining a match's distance with the weight of its corpus string
+/// into a rank.
+///
+/// Matches are sorted by ascending rank. Ties are broken by the higher weight,
+/// and then in the usual order of distance, score and corpus index.
+///
+/// Any `Fn(distance, weight) -> rank` closure is a blend.
+pub trait Blend: Send + Sync {
+    /// Returns the rank of a match `distance` away from the query, whose
+    /// corpus string has the given `weight`. Lower ranks are better.
+    fn rank(&self, distance: f64, weight: f64) -> f64;
+
+    /// Returns the largest distance at which a string weighing at most
+    /// `max_weight` can still rank at or below `rank`.
+    ///
+    /// Searches use this to only compare the query against nearby strings.
+    /// The default implementation returns infinity, so every string is
+    /// compared. Implementations must not return a smaller distance than
+    /// that of any such string.
+    fn max_distance(&self, rank: f64, max_weight: f64) -> f64 {
+        let _ = (rank, max_weight);
+        f64::INFINITY
+    }
+}
+
+impl<F: Fn(f64, f64) -> f64 + Send + Sync> Blend for F {
+    fn rank(&self, distance: f64, weight: f64) -> f64 {
+        self(distance, weight)
+    }
+}
+
+/// Ranks matches by distance, using weights only to break ties between
+/// equally distant matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ByDistance;
+
+impl ByDistance {
+    /// Creates a blend that ranks matches by distance.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Blend for ByDistance {
+    fn rank(&self, distance: f64, _weight: f64) -> f64 {
+        distance
+    }
+
+    fn max_distance(&self, rank: f64, _max_weight: f64) -> f64 {
+        rank
+    }
+}
+
+/// Ranks matches by their distance minus a bonus that grows with the
+/// logarithm of their weight, so that a much more frequent string can
+/// outrank a slightly closer one.
+///
+/// The rank is `distance - scale * log10(1 + weight)`: each tenfold increase
+/// in weight is worth about `scale` units of distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogWeight {
+    scale: f64,
+}
+
+impl LogWeight {
+    /// Creates a blend with a scale of 0.5, so that a string must be about a
+    /// hundred times more frequent to make up for one extra edit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the units of distance that each tenfold increase in weight is
+    /// worth.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    fn bonus(&self, weight: f64) -> f64 {
+        self.scale * weight.ln_1p() / std::f64::consts::LN_10
+    }
+}
+
+impl Default for LogWeight {
+    fn default() -> Self {
+        Self { scale: 0.5 }
+    }
+}
+
+impl Blend for LogWeight {
+    fn rank(&self, distance: f64, weight: f64) -> f64 {
+        distance - self.bonus(weight)
+    }
+
+    fn max_distance(&self, rank: f64, max_weight: f64) -> f64 {
+        // A negative scale favours light strings, which get no bonus at most.
+        rank + self.bonus(max_weight).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_weight_trades_distance_for_frequency() {
+        let blend = LogWeight::new();
+
+        assert_eq!(blend.rank(2.0, 0.0), 2.0);
+        assert!((blend.rank(1.0, 99.0) - 0.0).abs() < 1e-12);
+        assert!(blend.rank(2.0, 1e6) < blend.rank(1.0, 10.0));
+        assert!(LogWeight::new().with_scale(0.0).rank(2.0, 1e6) > blend.rank(1.0, 10.0));
+    }
+
+    #[test]
+    fn max_distances_bound_every_string_ranked_at_most_as_high() {
+        let blends: [&dyn Blend; 3] = [
+            &ByDistance::new(),
+            &LogWeight::new(),
+            &LogWeight::new().with_scale(-1.0),
+        ];
+
+        for blend in blends {
+            for (distance, weight) in [(0.0, 0.0), (1.0, 5.0), (3.0, 100.0), (7.0, 1e6)] {
+                let rank = blend.rank(distance, weight);
+                assert!(distance <= blend.max_distance(rank, 1e6));
+            }
+        }
+        assert_eq!(LogWeight::new().max_distance(1.0, 99.0), 2.0);
+    }
+
+    #[test]
+    fn closures_are_blends() {
+        let blend = |distance: f64, weight: f64| distance / (1.0 + weight);
+
+        assert_eq!(blend.rank(4.0, 1.0), 2.0);
+        assert_eq!(ByDistance::new().rank(4.0, 1.0), 4.0);
+        assert_eq!(blend.max_distance(2.0, 1.0), f64::INFINITY);
+    }
+}